@@ -0,0 +1,315 @@
+use crate::app::{format_time, ring_progress};
+use crate::config::TrayConfig;
+use crate::icon::{RingCache, Tone};
+use crate::ipc::{self, Command, Request, Response, Target, TimerInfo};
+use crate::tray::warn_offline;
+use anyhow::{Context, Result};
+use ksni::blocking::TrayMethods;
+use ksni::menu::{MenuItem, StandardItem, SubMenu};
+use ksni::{Category, Icon, OfflineReason, Status, ToolTip, Tray};
+use std::fs::{self, File};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How often the tray asks every process for its timers.
+const POLL: Duration = Duration::from_secs(1);
+
+/// Run the single tray icon of `mode = "aggregated"` for all tytimer
+/// processes, in the background.
+///
+/// Every process tries a lock file once per tick; whichever holds it owns the
+/// icon and gathers the timers over the control sockets. When it exits the
+/// lock passes to the next process, which takes over.
+///
+/// The returned flag is set while this process's icon cannot be seen, so its
+/// timers fall back as per-timer ones do. An owner whose icon fails to
+/// register gives the lock up, so each process in turn finds out the same.
+pub fn spawn(config: TrayConfig) -> Arc<AtomicBool> {
+    let offline = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&offline);
+    let result = thread::Builder::new()
+        .name("tytimer-tray".into())
+        .spawn(move || {
+            if let Err(err) = run(config, flag) {
+                eprintln!("Aggregated tray failed: {err}");
+            }
+        });
+    if let Err(err) = result {
+        warn_offline(format!("the aggregated tray failed to start: {err}"));
+        offline.store(true, Ordering::Relaxed);
+    }
+    offline
+}
+
+fn run(config: TrayConfig, offline: Arc<AtomicBool>) -> Result<()> {
+    let dir = ipc::runtime_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let path = dir.join("tray.lock");
+    let lock = File::create(&path).with_context(|| format!("opening {}", path.display()))?;
+    // Polled rather than blocked on, so waiting costs one try per tick; the
+    // lock goes with the process.
+    while unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::WouldBlock {
+            return Err(err).context("locking the tray");
+        }
+        thread::sleep(POLL);
+    }
+
+    let tray = AllTimersTray {
+        config,
+        timers: collect_timers(),
+        icons: RingCache::default(),
+        offline: Arc::clone(&offline),
+    };
+    let handle = match tray.spawn() {
+        Ok(handle) => handle,
+        Err(err) => {
+            warn_offline(&err);
+            offline.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+    };
+    loop {
+        thread::sleep(POLL);
+        let timers = collect_timers();
+        if handle.update(|tray| tray.timers = timers).is_none() {
+            return Ok(());
+        }
+    }
+}
+
+/// Every timer in every tytimer process, soonest to end first; ringing ones
+/// come before all others and stopwatches after.
+fn collect_timers() -> Vec<TimerInfo> {
+    let mut timers: Vec<TimerInfo> = ipc::broadcast(&Request::List)
+        .into_iter()
+        .flatten()
+        .flat_map(|response| match response {
+            Response::Timers { timers } => timers,
+            _ => Vec::new(),
+        })
+        .collect();
+    timers.sort_by_key(|timer| {
+        (
+            !timer.alarm,
+            timer.stopwatch,
+            !timer.running,
+            timer.remaining,
+        )
+    });
+    timers
+}
+
+/// Apply `command` to the timers matching `target`, without holding up the
+/// tray while the processes answer.
+fn send(target: Target, command: Command) {
+    thread::spawn(move || {
+        for response in ipc::broadcast(&Request::Control { target, command }) {
+            match response {
+                Ok(Response::Error { message }) => {
+                    eprintln!("A timer refused the request: {message}")
+                }
+                Err(err) => eprintln!("Could not reach a timer: {err}"),
+                Ok(_) => {}
+            }
+        }
+    });
+}
+
+struct AllTimersTray {
+    config: TrayConfig,
+    timers: Vec<TimerInfo>,
+    icons: RingCache,
+    /// Shared with the timers of this process, which show themselves while
+    /// it is set.
+    offline: Arc<AtomicBool>,
+}
+
+impl AllTimersTray {
+    /// The timer the icon shows: the first ringing one, or else the one
+    /// ending soonest.
+    fn soonest(&self) -> Option<&TimerInfo> {
+        self.timers.first()
+    }
+}
+
+impl Tray for AllTimersTray {
+    const MENU_ON_ACTIVATE: bool = true;
+
+    fn category(&self) -> Category {
+        Category::ApplicationStatus
+    }
+
+    fn watcher_online(&self) {
+        self.offline.store(false, Ordering::Relaxed);
+    }
+
+    /// Keep the service running for when the watcher comes back, and have
+    /// this process's timers show themselves meanwhile.
+    fn watcher_offline(&self, reason: OfflineReason) -> bool {
+        match reason {
+            OfflineReason::Error(err) => warn_offline(err),
+            _ => warn_offline("the StatusNotifier watcher went away"),
+        }
+        self.offline.store(true, Ordering::Relaxed);
+        true
+    }
+
+    fn id(&self) -> String {
+        "tytimer".into()
+    }
+
+    fn title(&self) -> String {
+        match self.soonest() {
+            Some(timer) => format!("{} ({})", name(timer), time(timer)),
+            None => "tytimer".into(),
+        }
+    }
+
+    fn status(&self) -> Status {
+        match self.soonest() {
+            None => Status::Passive,
+            Some(timer) if timer.alarm => Status::NeedsAttention,
+            Some(_) => Status::Active,
+        }
+    }
+
+    fn icon_name(&self) -> String {
+        if self.config.progress_icon {
+            String::new()
+        } else {
+            "alarm-symbolic".into()
+        }
+    }
+
+    fn icon_pixmap(&self) -> Vec<Icon> {
+        if !self.config.progress_icon {
+            return Vec::new();
+        }
+        let Some(timer) = self.soonest() else {
+            return self.icons.progress_ring(0.0, Tone::Paused, None);
+        };
+        let tone = if timer.alarm {
+            Tone::Ringing
+        } else if !timer.running {
+            Tone::Paused
+        } else {
+            Tone::Running
+        };
+        let progress = ring_progress(
+            timer.remaining.saturating_mul(1000),
+            timer.original,
+            timer.stopwatch,
+            timer.alarm && timer.remaining < 0,
+        );
+        let digits = self
+            .config
+            .digits
+            .then(|| match timer.remaining.abs() / 60 {
+                minutes if minutes >= 100 => format!("{}h", minutes / 60),
+                minutes => minutes.to_string(),
+            });
+        self.icons.progress_ring(progress, tone, digits.as_deref())
+    }
+
+    fn attention_icon_pixmap(&self) -> Vec<Icon> {
+        self.icon_pixmap()
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            icon_name: self.icon_name(),
+            title: match self.timers.len() {
+                0 => "No timers running".into(),
+                1 => "1 timer".into(),
+                n => format!("{n} timers"),
+            },
+            description: self
+                .timers
+                .iter()
+                .map(|timer| format!("{}: {}", name(timer), time(timer)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ..Default::default()
+        }
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        if self.timers.is_empty() {
+            return vec![StandardItem {
+                label: "No timers running".into(),
+                enabled: false,
+                ..Default::default()
+            }
+            .into()];
+        }
+
+        let mut items: Vec<MenuItem<Self>> = self
+            .timers
+            .iter()
+            .map(|timer| {
+                let id = || Target::Match(timer.id.clone());
+                let mut submenu = vec![if timer.running {
+                    command_item("Pause", id(), Command::Pause)
+                } else {
+                    command_item("Resume", id(), Command::Resume)
+                }];
+                if !timer.stopwatch {
+                    submenu.push(command_item(
+                        "+1 min",
+                        id(),
+                        Command::Extend { seconds: 60 },
+                    ));
+                    submenu.push(command_item(
+                        "+5 min",
+                        id(),
+                        Command::Extend { seconds: 300 },
+                    ));
+                }
+                submenu.push(command_item("Stop", id(), Command::Stop));
+                SubMenu {
+                    label: format!("{} · {}", name(timer), time(timer)),
+                    submenu,
+                    ..Default::default()
+                }
+                .into()
+            })
+            .collect();
+        items.push(MenuItem::Separator);
+        items.push(command_item("Pause all", Target::All, Command::Pause));
+        items.push(command_item("Resume all", Target::All, Command::Resume));
+        items.push(command_item("Stop all", Target::All, Command::Stop));
+        items
+    }
+}
+
+/// A menu entry that sends `command` to the timers matching `target`.
+fn command_item(label: &str, target: Target, command: Command) -> MenuItem<AllTimersTray> {
+    StandardItem {
+        label: label.into(),
+        activate: Box::new(move |_: &mut AllTimersTray| send(target.clone(), command)),
+        ..Default::default()
+    }
+    .into()
+}
+
+fn name(timer: &TimerInfo) -> &str {
+    timer.label.as_deref().unwrap_or(&timer.id)
+}
+
+/// e.g. `4:59`, `4:59 paused`, `+0:12 over` or `12:03 elapsed`.
+fn time(timer: &TimerInfo) -> String {
+    if timer.stopwatch {
+        format!("{} elapsed", format_time(-timer.remaining))
+    } else if timer.remaining < 0 {
+        format!("+{} over", format_time(-timer.remaining))
+    } else if timer.running {
+        format_time(timer.remaining)
+    } else {
+        format!("{} paused", format_time(timer.remaining))
+    }
+}
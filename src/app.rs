@@ -1,68 +1,170 @@
-use crate::audio::AudioPlayer;
-use crate::tray::{TrayAction, TrayHandle};
-use crate::ui::TimerWindow;
-use gtk4::prelude::*;
+use crate::audio::{AlarmOptions, AudioPlayer, Cue};
+use crate::config::{Config, TrayMode};
+use crate::dbus::{DbusService, DbusTimer};
+use crate::duration;
+use crate::icon::Tone;
+use crate::interval::{self, Intervals};
+use crate::ipc::{Target, TimerInfo};
+use crate::notify::Notifier;
+use crate::persist;
+use crate::pomodoro::Pomodoro;
+use crate::stopwatch::{self, Stopwatch};
+use crate::timer::{TimerSpec, TimerState};
+use crate::tray::{TrayAction, TrayHandle, TrayStatus};
+use crate::ui::{CountdownPill, TimerWindow};
+use crate::wallclock;
+use crate::warning::Warning;
 use gtk4::Application;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::mpsc;
-use std::time::Duration;
-
-#[derive(Debug, Clone)]
-struct TimerState {
-    original: i64,
-    remaining: i64,
-    running: bool,
-    alarm_triggered: bool,
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+type ClosedHandler = Box<dyn Fn(&str)>;
+
+/// Why a timer turned an action down.
+#[derive(Debug, Clone, Copy)]
+pub enum Refusal {
+    /// Extending or snoozing a stopwatch, which counts up and has no end.
+    Stopwatch,
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::Stopwatch => f.write_str("a stopwatch has no end to move"),
+        }
+    }
 }
 
 pub struct TimerApp {
-    app: Application,
+    /// `<pid>-<n>`, unique among running timers.
+    id: String,
+    spec: TimerSpec,
     window: TimerWindow,
     tray: TrayHandle,
+    /// Stands in for the tray icon while no tray host is running.
+    pill: Option<CountdownPill>,
     state: Rc<RefCell<TimerState>>,
     audio: AudioPlayer,
+    /// The session's progress, for a pomodoro timer.
+    pomodoro: RefCell<Option<Pomodoro>>,
+    /// The workout's progress, for an interval timer.
+    intervals: RefCell<Option<Intervals>>,
+    /// The laps so far, for a stopwatch.
+    stopwatch: RefCell<Option<Stopwatch>>,
+    /// Where the laps were written, once the stopwatch is being stopped.
+    laps_file: RefCell<Option<PathBuf>>,
+    /// The last 3-2-1 countdown second that was beeped.
+    beeped: Cell<Option<i64>>,
+    /// Which of the timer's `--warn` thresholds have chimed.
+    warned: RefCell<Vec<bool>>,
+    /// When the alarm last rang, while it has not been acknowledged.
+    alerted_at: Cell<Option<Instant>>,
+    dbus: RefCell<Option<DbusTimer>>,
+    /// Desktop notifications, when the timer asked for them.
+    notifier: Option<Notifier>,
+    /// The ticket of the notification for the current alarm, while it is up.
+    notification: Cell<Option<u32>>,
+    /// Where notification buttons send their actions.
+    action_tx: mpsc::Sender<TrayAction>,
     tick_source: RefCell<Option<glib::SourceId>>,
+    action_source: RefCell<Option<glib::SourceId>>,
+    on_closed: RefCell<Option<ClosedHandler>>,
+    closed: Cell<bool>,
 }
 
 impl TimerApp {
-    pub fn new(app: &Application, total_seconds: i64) -> Rc<Self> {
+    pub fn new(
+        app: &Application,
+        spec: TimerSpec,
+        dbus: Option<&DbusService>,
+        notifier: Option<Notifier>,
+        shared_tray: Option<Arc<AtomicBool>>,
+        config: &Config,
+    ) -> anyhow::Result<Rc<Self>> {
+        let state = spec.state()?;
         let (action_tx, action_rx) = mpsc::channel::<TrayAction>();
 
-        let tray = TrayHandle::spawn(action_tx.clone()).unwrap_or_else(|err| {
-            eprintln!("Tray failed to start: {err}");
+        // In aggregated mode the process-wide icon shows this timer.
+        let tray = if let Some(offline) = shared_tray {
+            TrayHandle::shared(offline)
+        } else if config.tray.enabled && config.tray.mode == TrayMode::PerTimer {
+            TrayHandle::spawn(action_tx.clone(), spec.label.clone(), config)
+                .unwrap_or_else(TrayHandle::unavailable)
+        } else {
             TrayHandle::noop(action_tx.clone())
-        });
+        };
+        let pill = (config.tray.enabled && config.tray.fallback)
+            .then(|| CountdownPill::new(app, spec.label.as_deref(), config));
 
-        let window = TimerWindow::new(app, total_seconds);
+        let window = TimerWindow::new(
+            app,
+            state.original,
+            spec.label.as_deref(),
+            spec.message.as_deref(),
+            config,
+        );
         let audio = AudioPlayer::new();
-        let state = Rc::new(RefCell::new(TimerState {
-            original: total_seconds,
-            remaining: total_seconds,
-            running: true,
-            alarm_triggered: false,
-        }));
 
-        let app_clone = app.clone();
+        let id = format!(
+            "{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let dbus = dbus.and_then(|service| {
+            service
+                .register(
+                    timer_info(&id, &spec, &state),
+                    action_tx.clone(),
+                )
+                .map_err(|err| eprintln!("D-Bus export failed: {err}"))
+                .ok()
+        });
+        let warned = passed_warnings(&spec.alarm.warn, &state);
+        let state = Rc::new(RefCell::new(state));
 
         let this = Rc::new(Self {
-            app: app_clone,
+            id,
+            pomodoro: RefCell::new(spec.pomodoro.clone()),
+            intervals: RefCell::new(spec.intervals.clone()),
+            stopwatch: RefCell::new(spec.stopwatch.clone()),
+            laps_file: RefCell::new(None),
+            beeped: Cell::new(None),
+            warned: RefCell::new(warned),
+            spec,
             window,
             tray,
+            pill,
             state: state.clone(),
             audio,
+            alerted_at: Cell::new(None),
+            dbus: RefCell::new(dbus),
+            notifier,
+            notification: Cell::new(None),
+            action_tx,
             tick_source: RefCell::new(None),
+            action_source: RefCell::new(None),
+            on_closed: RefCell::new(None),
+            closed: Cell::new(false),
         });
 
         // Connect tray actions into GTK main loop
         {
             let app = Rc::clone(&this);
-            glib::timeout_add_local(Duration::from_millis(100), move || {
+            let source = glib::timeout_add_local(Duration::from_millis(100), move || {
                 while let Ok(action) = action_rx.try_recv() {
                     app.handle_action(action);
                 }
                 glib::ControlFlow::Continue
             });
+            *this.action_source.borrow_mut() = Some(source);
         }
 
         // Connect window buttons
@@ -72,22 +174,52 @@ impl TimerApp {
                 app.quit();
             });
         }
-        for pct in [1_u64, 5, 10] {
+        {
             let app = Rc::clone(&this);
-            this.window.connect_pause(pct, move || app.pause_for_percent(pct));
+            this.window
+                .connect_snooze(move |snooze| app.handle_action(TrayAction::SnoozeBy(snooze)));
+        }
+        {
+            let app = Rc::clone(&this);
+            this.window.connect_lap(move || app.handle_action(TrayAction::Lap));
+        }
+        if let Some(stopwatch) = this.stopwatch.borrow().as_ref() {
+            this.window.set_stopwatch(true);
+            this.window.set_laps(&stopwatch.describe_laps());
         }
         {
             let app = Rc::clone(&this);
             this.window.connect_close(move || app.window.hide());
         }
+        if let Some(pill) = &this.pill {
+            let app = Rc::clone(&this);
+            pill.connect_toggle(move || app.handle_action(TrayAction::ToggleRunning));
+            let app = Rc::clone(&this);
+            pill.connect_stop(move || app.quit());
+        }
 
-        this.window.set_remaining(total_seconds);
+        this.save();
+        this.refresh();
+        Ok(this)
+    }
 
-        this.update_tray();
-        this
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Called with the timer's id once it has been stopped and torn down.
+    pub fn connect_closed<F>(&self, handler: F)
+    where
+        F: Fn(&str) + 'static,
+    {
+        *self.on_closed.borrow_mut() = Some(Box::new(handler));
     }
 
     pub fn present(self: &Rc<Self>) {
+        // A workout or stopwatch is followed on screen from the start.
+        if self.intervals.borrow().is_some() || self.stopwatch.borrow().is_some() {
+            self.window.show();
+        }
         self.start_tick();
     }
 
@@ -96,8 +228,10 @@ impl TimerApp {
             source.remove();
         }
 
+        // Poll a few times a second: the remaining time is derived from the
+        // deadline, so this only controls how promptly a change is noticed.
         let app = Rc::clone(self);
-        let source = glib::timeout_add_seconds_local(1, move || {
+        let source = glib::timeout_add_local(Duration::from_millis(250), move || {
             app.on_tick();
             glib::ControlFlow::Continue
         });
@@ -105,68 +239,551 @@ impl TimerApp {
     }
 
     fn on_tick(self: &Rc<Self>) {
+        // A stopwatch has no end to reach.
+        if self.stopwatch.borrow().is_some() {
+            self.refresh();
+            return;
+        }
+        if self.pomodoro.borrow().is_some() {
+            if self.state.borrow().is_due() {
+                self.next_phase(true);
+                self.save();
+            }
+            self.refresh();
+            return;
+        }
+        if self.intervals.borrow().is_some() && !self.state.borrow().alarm_triggered {
+            self.interval_tick();
+        } else {
+            self.warn_tick();
+        }
+
         let mut state = self.state.borrow_mut();
-        if state.running {
-            state.remaining -= 1;
-            if state.remaining <= 0 && !state.alarm_triggered {
-                self.window.show();
-                self.audio.play_alarm();
-                state.alarm_triggered = true;
+        if state.is_due() {
+            if let Some(missed) = state.missed {
+                self.window.set_notice(Some(&format!(
+                    "Missed: ended at {} while tytimer was not running",
+                    wallclock::format_local(missed)
+                )));
+            }
+            state.alarm_triggered = true;
+            self.alert();
+            if let Some(dbus) = self.dbus.borrow().as_ref() {
+                dbus.finished();
             }
         }
+        let ringing = state.alarm_triggered;
         drop(state);
-        self.window.set_remaining(self.state.borrow().remaining);
+        if ringing {
+            self.follow_up_alarm();
+        }
+        self.refresh();
+    }
+
+    /// Start the pomodoro session's next phase, announcing it with the
+    /// phase's sound when it was reached by running out.
+    fn next_phase(&self, announce: bool) {
+        let mut session = self.pomodoro.borrow_mut();
+        let Some(session) = session.as_mut() else {
+            return;
+        };
+        session.advance();
+        let seconds = session.phase_seconds();
+        *self.state.borrow_mut() = TimerState::new(seconds);
+        self.window.set_original(seconds);
+        self.window.set_notice(Some(&session.announcement()));
+        if announce {
+            let options = AlarmOptions {
+                sound: session
+                    .sound()
+                    .map(Path::to_path_buf)
+                    .or_else(|| self.spec.alarm.sound.clone()),
+                repeat: false,
+                ..AlarmOptions::default()
+            };
+            self.audio.play_alarm(&options);
+            self.window.show();
+        }
+    }
+
+    /// Beep the 3-2-1 countdown and move between rounds. The end of the last
+    /// round is left to ring like any other timer.
+    fn interval_tick(&self) {
+        let (remaining, due) = {
+            let state = self.state.borrow();
+            if !state.running() {
+                return;
+            }
+            (state.remaining(), state.is_due())
+        };
+        if (1..=3).contains(&remaining) && self.beeped.get() != Some(remaining) {
+            self.beeped.set(Some(remaining));
+            self.audio.play_cue(Cue::Countdown);
+        }
+        if due && self.next_round() {
+            self.save();
+        }
+    }
+
+    /// Chime once for each `--warn` threshold as the timer passes it. A
+    /// threshold the timer is moved back before (by extending it) re-arms.
+    fn warn_tick(&self) {
+        let state = self.state.borrow();
+        if !state.running() || state.alarm_triggered {
+            return;
+        }
+        let remaining = state.remaining();
+        let mut chime = false;
+        let mut warned = self.warned.borrow_mut();
+        for (warning, warned) in self.spec.alarm.warn.iter().zip(warned.iter_mut()) {
+            let at = warning.seconds_left(state.original);
+            if remaining > at {
+                *warned = false;
+            } else if !*warned && remaining > 0 {
+                *warned = true;
+                chime = true;
+            }
+        }
+        if chime {
+            self.audio.play_cue(Cue::Warning);
+        }
+    }
+
+    /// Start the workout's next phase with its cue; `false` after the last round.
+    fn next_round(&self) -> bool {
+        let mut intervals = self.intervals.borrow_mut();
+        let Some(intervals) = intervals.as_mut() else {
+            return false;
+        };
+        if !intervals.advance() {
+            return false;
+        }
+        let seconds = intervals.phase_seconds();
+        *self.state.borrow_mut() = TimerState::new(seconds);
+        self.window.set_original(seconds);
+        self.beeped.set(None);
+        self.audio.play_cue(match intervals.phase {
+            interval::Phase::Work => Cue::Work,
+            interval::Phase::Rest => Cue::Rest,
+        });
+        true
+    }
+
+    /// Ring and bring the alarm window up.
+    fn alert(&self) {
+        self.window.show_message(true);
+        self.window.show();
+        self.audio.play_alarm(&self.spec.alarm);
+        self.alerted_at.set(Some(Instant::now()));
+        self.notify();
+    }
+
+    /// Put up a desktop notification for the alarm, replacing any earlier one.
+    fn notify(&self) {
+        let Some(notifier) = &self.notifier else {
+            return;
+        };
+        if let Some(id) = self.notification.take() {
+            notifier.close(id);
+        }
+        let summary = match &self.spec.label {
+            Some(label) => format!("{label}: time's up"),
+            None => "Time's up".to_string(),
+        };
+        let body = self.spec.message.clone().unwrap_or_else(|| {
+            let original = self.state.borrow().original;
+            format!("{} timer finished", duration::format(original))
+        });
+        let ticket = notifier.show(&summary, &body, self.action_tx.clone());
+        self.notification.set(Some(ticket));
+    }
+
+    /// Apply the timer's `ring_for` and `realert` options to an alarm nobody
+    /// has answered yet.
+    fn follow_up_alarm(&self) {
+        let Some(alerted_at) = self.alerted_at.get() else {
+            return;
+        };
+        let since = alerted_at.elapsed();
+        let options = &self.spec.alarm;
+        if let Some(realert) = options.realert.filter(|secs| *secs > 0) {
+            if since >= Duration::from_secs(realert as u64) {
+                self.alert();
+                return;
+            }
+        }
+        if let Some(ring_for) = options.ring_for {
+            if since >= Duration::from_secs(ring_for.max(0) as u64) {
+                self.audio.stop();
+            }
+        }
+    }
+
+    /// Silence the alarm once it has been answered.
+    fn acknowledge(&self) {
+        self.alerted_at.set(None);
+        if let (Some(notifier), Some(id)) = (&self.notifier, self.notification.take()) {
+            notifier.close(id);
+        }
+        self.audio.stop();
+        self.window.show_message(false);
+    }
+
+    /// Bring every view of the timer up to date with its state.
+    fn refresh(&self) {
+        self.update_window();
         self.update_tray();
+        if let Some(dbus) = self.dbus.borrow().as_ref() {
+            dbus.update(self.info());
+        }
+    }
+
+    fn update_window(&self) {
+        let state = self.state.borrow();
+        let progress = progress(&state, self.stopwatch.borrow().is_some());
+        if self.stopwatch.borrow().is_some() {
+            let elapsed = stopwatch::format_ms(state.elapsed_ms());
+            let tone = if state.running() { Tone::Running } else { Tone::Paused };
+            self.window.set_progress(progress, tone, &elapsed);
+            self.window.set_elapsed(&elapsed);
+            return;
+        }
+        let target = state.target().map(wallclock::format_local);
+        let overtime = state.overtime();
+        match overtime {
+            0 => self.window.set_remaining(state.remaining(), target.as_deref()),
+            overtime => self.window.set_overtime(overtime),
+        }
+        // Calm, then amber from a `--warn` threshold or the last quarter,
+        // orange for the last tenth and red once over.
+        let tone = if !state.running() {
+            Tone::Paused
+        } else if overtime > 0 {
+            Tone::Ringing
+        } else if state.remaining() * 10 <= state.original {
+            Tone::Urgent
+        } else if self.warned.borrow().contains(&true) || state.remaining() * 4 <= state.original {
+            Tone::Warning
+        } else {
+            Tone::Running
+        };
+        let digits = match overtime {
+            0 => format_time(state.remaining()),
+            overtime => format!("+{}", format_time(overtime)),
+        };
+        self.window.set_progress(progress, tone, &digits);
+        let pomodoro = self.pomodoro.borrow().as_ref().map(Pomodoro::describe);
+        self.window.set_phase(pomodoro.as_deref());
+        let round = self.intervals.borrow().as_ref().map(Intervals::describe);
+        self.window.set_round(round.as_deref());
     }
 
     fn update_tray(&self) {
         let state = self.state.borrow();
-        let label = format_time(state.remaining);
+        let stopwatch = self.stopwatch.borrow().is_some();
+        let overtime = state.overtime();
+        let status = TrayStatus {
+            time: match overtime {
+                _ if stopwatch => format_time(state.elapsed_ms() / 1000),
+                0 => format_time(state.remaining()),
+                overtime => format!("+{} over", format_time(overtime)),
+            },
+            target: state.target().map(wallclock::format_local),
+            ends: (state.running() && !stopwatch && overtime == 0).then(|| {
+                let ends = SystemTime::now() + Duration::from_millis(state.remaining_ms().max(0) as u64);
+                wallclock::format_local(ends)
+            }),
+            running: state.running(),
+            phase: self.phase(),
+            stopwatch,
+            overtime: overtime > 0,
+            warning: !state.alarm_triggered && self.warned.borrow().contains(&true),
+            progress: progress(&state, stopwatch),
+            minutes: match overtime {
+                _ if stopwatch => state.elapsed_ms() / 60_000,
+                0 => (state.remaining() + 59).div_euclid(60).max(0),
+                overtime => overtime / 60,
+            },
+            can_shorten: !stopwatch && overtime == 0 && state.remaining() > 60,
+        };
         self.tray
-            .update_state(&label, state.running)
+            .update_state(&status)
             .unwrap_or_else(|err| eprintln!("Failed to update tray: {err}"));
+        if let Some(pill) = &self.pill {
+            // The alarm window has controls of its own.
+            let visible = self.tray.is_offline() && !self.window.is_visible();
+            if visible {
+                pill.update(&status);
+            }
+            pill.set_visible(visible);
+        }
     }
 
-    fn pause_for_percent(&self, percent: u64) {
-        let mut state = self.state.borrow_mut();
-        let new_remaining = ((state.original as f64) * (percent as f64 / 100.0)).round() as i64;
-        state.remaining = new_remaining.max(1);
-        state.running = true;
-        state.alarm_triggered = false;
-        self.window.hide();
-        drop(state);
-        self.audio.stop();
-        self.update_tray();
+    /// The pomodoro phase or interval round, e.g. `Work 2/4`.
+    fn phase(&self) -> Option<String> {
+        let pomodoro = self.pomodoro.borrow().as_ref().map(Pomodoro::describe);
+        pomodoro.or_else(|| self.intervals.borrow().as_ref().map(Intervals::describe))
     }
 
-    fn handle_action(&self, action: TrayAction) {
+    /// Whether the timer `info` describes takes `action`. What this refuses,
+    /// [`handle_action`](Self::handle_action) ignores; D-Bus and the control
+    /// socket ask first so they can tell the caller why.
+    pub fn check(info: &TimerInfo, action: TrayAction) -> Result<(), Refusal> {
+        match action {
+            TrayAction::Extend(_) | TrayAction::Snooze(_) | TrayAction::SnoozeBy(_)
+                if info.stopwatch =>
+            {
+                Err(Refusal::Stopwatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn handle_action(&self, action: TrayAction) {
+        if let Err(refusal) = Self::check(&self.info(), action) {
+            eprintln!("Ignoring {action:?} for {}: {refusal}", self.id);
+            return;
+        }
         match action {
             TrayAction::ToggleRunning => {
+                self.state.borrow_mut().toggle();
+            }
+            TrayAction::Pause => {
+                self.state.borrow_mut().pause();
+            }
+            TrayAction::Resume => {
+                self.state.borrow_mut().resume();
+            }
+            TrayAction::Extend(seconds) => {
                 let mut state = self.state.borrow_mut();
-                state.running = !state.running;
+                let was_ringing = state.alarm_triggered;
+                state.shift(seconds);
+                if was_ringing && !state.alarm_triggered {
+                    self.acknowledge();
+                    self.window.hide();
+                }
+            }
+            TrayAction::Snooze(seconds) => {
+                self.state.borrow_mut().snooze(seconds);
+                self.acknowledge();
+                self.window.hide();
+            }
+            TrayAction::SnoozeBy(snooze) => {
+                let original = self.state.borrow().original;
+                self.handle_action(TrayAction::Snooze(snooze.seconds(original)));
+                return;
+            }
+            TrayAction::Restart => {
+                self.restart();
+            }
+            TrayAction::SkipPhase if self.intervals.borrow().is_some() => {
+                // Skipping the last round ends the workout.
+                if !self.next_round() {
+                    let mut state = self.state.borrow_mut();
+                    let remaining = state.remaining();
+                    state.shift(-remaining);
+                }
+            }
+            TrayAction::SkipPhase => {
+                self.next_phase(false);
+            }
+            TrayAction::Lap => {
+                let mut stopwatch = self.stopwatch.borrow_mut();
+                if let Some(stopwatch) = stopwatch.as_mut() {
+                    stopwatch.lap(self.state.borrow().elapsed_ms());
+                    self.window.set_laps(&stopwatch.describe_laps());
+                }
             }
             TrayAction::ShowAlarm => {
                 self.window.show();
             }
+            TrayAction::NotificationFailed => {
+                self.notification.set(None);
+                self.window
+                    .set_notice(Some("The desktop notification could not be shown"));
+            }
             TrayAction::Quit => {
                 self.quit();
                 return;
             }
         }
-        self.update_tray();
+        self.save();
+        self.refresh();
+    }
+
+    /// Run the timer (or the current phase) again from its full length,
+    /// silencing it if it was ringing.
+    fn restart(&self) {
+        let mut state = self.state.borrow_mut();
+        let original = state.original;
+        state.restart_with(original);
+        state.snoozes = 0;
+        *self.warned.borrow_mut() = passed_warnings(&self.spec.alarm.warn, &state);
+        drop(state);
+        if let Some(stopwatch) = self.stopwatch.borrow_mut().as_mut() {
+            stopwatch.laps.clear();
+            self.window.set_laps(&[]);
+        }
+        self.beeped.set(None);
+        self.window.set_notice(None);
+        self.acknowledge();
+    }
+
+    /// Write the timer's current progress to its state file.
+    fn save(&self) {
+        let mut spec = self.spec.clone();
+        spec.restore = Some(self.state.borrow().snapshot());
+        spec.pomodoro = self.pomodoro.borrow().clone();
+        spec.intervals = self.intervals.borrow().clone();
+        spec.stopwatch = self.stopwatch.borrow().clone();
+        if let Err(err) = persist::save(&self.id, &spec) {
+            eprintln!("Failed to save timer state: {err}");
+        }
+    }
+
+    /// Log how the timer ended, overtime included, to the history file.
+    fn record_history(&self) {
+        let state = self.state.borrow();
+        let entry = persist::HistoryEntry {
+            label: self.spec.label.clone(),
+            original: state.original,
+            stopped_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or_default(),
+            alarm: state.alarm_triggered,
+            overtime: state.overtime(),
+            snoozes: state.snoozes,
+        };
+        if let Err(err) = persist::record_history(&entry) {
+            eprintln!("Failed to record history: {err}");
+        }
+    }
+
+    pub fn info(&self) -> TimerInfo {
+        timer_info(&self.id, &self.spec, &self.state.borrow())
+    }
+
+    fn is_stopwatch(&self) -> bool {
+        self.stopwatch.borrow().is_some()
+    }
+
+    /// Write a stopwatch's laps to their file ahead of stopping it, so the
+    /// caller can say where they went; `None` for other timers.
+    pub fn save_laps(&self) -> Option<PathBuf> {
+        let stopwatch = self.stopwatch.borrow();
+        let stopwatch = stopwatch.as_ref()?;
+        if let Some(path) = self.laps_file.borrow().as_ref() {
+            return Some(path.clone());
+        }
+        let label = self.spec.label.as_deref().unwrap_or("stopwatch");
+        match stopwatch.export(label, self.state.borrow().elapsed_ms()) {
+            Ok(path) => {
+                *self.laps_file.borrow_mut() = Some(path.clone());
+                Some(path)
+            }
+            Err(err) => {
+                eprintln!("Failed to save laps: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn matches(&self, target: &Target) -> bool {
+        match target {
+            Target::All => true,
+            Target::Match(name) => {
+                *name == self.id || self.spec.label.as_deref() == Some(name.as_str())
+            }
+        }
     }
 
     fn quit(&self) {
-        self.audio.stop();
+        if self.closed.replace(true) {
+            return;
+        }
+        self.acknowledge();
+        if self.is_stopwatch() {
+            if let Some(path) = self.save_laps() {
+                eprintln!("Laps saved to {}", path.display());
+            }
+        } else {
+            self.record_history();
+        }
+        persist::remove(&self.id);
+        self.dbus.borrow_mut().take();
         if let Some(source) = self.tick_source.borrow_mut().take() {
             source.remove();
         }
+        if let Some(source) = self.action_source.borrow_mut().take() {
+            source.remove();
+        }
         self.tray.shutdown();
-        self.app.quit();
+        if let Some(pill) = &self.pill {
+            pill.destroy();
+        }
+        self.window.destroy();
+        if let Some(on_closed) = self.on_closed.borrow_mut().take() {
+            on_closed(&self.id);
+        }
+    }
+}
+
+/// Share of the time left, for the progress ring in the tray and window.
+fn progress(state: &TimerState, stopwatch: bool) -> f64 {
+    ring_progress(
+        state.remaining_ms(),
+        state.original,
+        stopwatch,
+        state.overtime() > 0,
+    )
+}
+
+/// Share of the time left of a timer set for `original` seconds, from the
+/// milliseconds it has left; also used by the aggregated tray, which only
+/// knows the timer from its `TimerInfo`.
+pub fn ring_progress(remaining_ms: i64, original: i64, stopwatch: bool, overtime: bool) -> f64 {
+    let original_ms = original.max(1).saturating_mul(1000);
+    if stopwatch {
+        // Sweeps round once a minute.
+        let elapsed_ms = original.saturating_mul(1000).saturating_sub(remaining_ms);
+        (elapsed_ms % 60_000) as f64 / 60_000.0
+    } else if overtime {
+        1.0
+    } else {
+        remaining_ms as f64 / original_ms as f64
     }
 }
 
-fn format_time(total_seconds: i64) -> String {
+/// Which of `warnings` are already behind `state`; those stay quiet, as a
+/// heads-up is only useful when the timer runs into it.
+fn passed_warnings(warnings: &[Warning], state: &TimerState) -> Vec<bool> {
+    warnings
+        .iter()
+        .map(|warning| state.remaining() <= warning.seconds_left(state.original))
+        .collect()
+}
+
+fn timer_info(id: &str, spec: &TimerSpec, state: &TimerState) -> TimerInfo {
+    let ends_at = state
+        .running()
+        .then(|| SystemTime::now() + Duration::from_millis(state.remaining_ms().max(0) as u64))
+        .and_then(|end| end.duration_since(UNIX_EPOCH).ok())
+        .map(|end| end.as_secs());
+    TimerInfo {
+        id: id.to_string(),
+        label: spec.label.clone(),
+        pid: std::process::id(),
+        remaining: state.remaining(),
+        original: state.original,
+        running: state.running(),
+        alarm: state.alarm_triggered,
+        stopwatch: spec.stopwatch.is_some(),
+        ends_at,
+    }
+}
+
+pub fn format_time(total_seconds: i64) -> String {
     let sign = total_seconds < 0;
     let abs = total_seconds.abs();
     let minutes = abs / 60;
@@ -177,3 +794,4 @@ fn format_time(total_seconds: i64) -> String {
         format!("{minutes}:{seconds:02}")
     }
 }
+
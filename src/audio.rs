@@ -1,132 +1,378 @@
+use crate::warning::Warning;
 use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 // Embed the UA.mp3 file into the binary
 static ALARM_SOUND: &[u8] = include_bytes!("../UA.mp3");
 
+/// Volume a ramped alarm starts at, as a fraction of full volume.
+const RAMP_START: f64 = 0.1;
+
+/// How a timer's alarm rings; set per timer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlarmOptions {
+    /// Audio file to play instead of the embedded sound.
+    pub sound: Option<PathBuf>,
+    /// Keep repeating the sound until the alarm is stopped or snoozed.
+    pub repeat: bool,
+    /// Seconds over which the volume rises from quiet to full.
+    pub ramp: Option<i64>,
+    /// Stop repeating after this many seconds; the alarm stays unacknowledged.
+    pub ring_for: Option<i64>,
+    /// While unacknowledged, ring again (and re-show the window) this often.
+    pub realert: Option<i64>,
+    /// Also send a desktop notification with Stop and Snooze buttons.
+    pub notify: bool,
+    /// Chime softly when this much is left, before the alarm proper.
+    pub warn: Vec<Warning>,
+}
+
+impl Default for AlarmOptions {
+    fn default() -> Self {
+        Self {
+            sound: None,
+            repeat: true,
+            ramp: None,
+            ring_for: None,
+            realert: None,
+            notify: false,
+            warn: Vec::new(),
+        }
+    }
+}
+
+/// A pipeline that is playing, kept alive together with its bus watch.
+struct Playback {
+    pipeline: gstreamer::Pipeline,
+    _watch: gstreamer::bus::BusWatchGuard,
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gstreamer::State::Null);
+    }
+}
+
+/// Short synthesized beeps: the rounds of interval training, and the chime
+/// for a `--warn` threshold on any timer.
+#[derive(Debug, Clone, Copy)]
+pub enum Cue {
+    /// One of the 3-2-1 beeps before a phase ends.
+    Countdown,
+    /// A work phase starts.
+    Work,
+    /// A rest phase starts.
+    Rest,
+    /// A timer is close to its end (`--warn`).
+    Warning,
+}
+
+impl Cue {
+    /// Pitch in Hz, length in milliseconds and volume.
+    fn tone(self) -> (f64, u64, f64) {
+        match self {
+            Cue::Countdown => (660.0, 120, 0.8),
+            Cue::Work => (1320.0, 450, 0.8),
+            Cue::Rest => (440.0, 450, 0.8),
+            // Kept soft so it never passes for the alarm itself.
+            Cue::Warning => (880.0, 600, 0.25),
+        }
+    }
+}
+
 pub struct AudioPlayer {
-    player: RefCell<Option<gstreamer::Element>>,
+    player: Rc<RefCell<Option<Playback>>>,
+    /// Kept apart from the alarm so a beep never cuts it off.
+    cue: RefCell<Option<gstreamer::Pipeline>>,
 }
 
 impl AudioPlayer {
     pub fn new() -> Self {
         Self {
-            player: RefCell::new(None),
+            player: Rc::new(RefCell::new(None)),
+            cue: RefCell::new(None),
         }
     }
 
-    pub fn play_alarm(&self) {
-        self.stop();
+    pub fn play_cue(&self, cue: Cue) {
+        self.stop_cue();
 
-        // Create a pipeline with appsrc for memory playback
+        let (freq, millis, volume) = cue.tone();
         let pipeline = gstreamer::Pipeline::new();
-
-        let appsrc = match gstreamer::ElementFactory::make("appsrc")
-            .name("tytimer-src")
-            .build()
-        {
-            Ok(element) => element,
-            Err(_) => return,
+        // 10ms buffers at 44.1kHz, so the buffer count sets the length.
+        let source = gstreamer::ElementFactory::make("audiotestsrc")
+            .name("tytimer-cue")
+            .property_from_str("wave", "sine")
+            .property("freq", freq)
+            .property("volume", volume)
+            .property("samplesperbuffer", 441_i32)
+            .property("num-buffers", (millis / 10) as i32)
+            .build();
+        let convert = gstreamer::ElementFactory::make("audioconvert")
+            .name("tytimer-cue-convert")
+            .build();
+        let (Ok(source), Ok(convert), Some(sink)) = (source, convert, make_sink()) else {
+            return;
         };
-
-        let decodebin = match gstreamer::ElementFactory::make("decodebin")
-            .name("tytimer-decoder")
-            .build()
+        if pipeline.add_many([&source, &convert, &sink]).is_err()
+            || gstreamer::Element::link_many([&source, &convert, &sink]).is_err()
         {
-            Ok(element) => element,
-            Err(_) => return,
-        };
+            return;
+        }
 
-        let audio_sink = gstreamer::ElementFactory::make("pipewiresink")
-            .name("tytimer-pw-sink")
-            .build()
-            .or_else(|_| {
-                gstreamer::ElementFactory::make("pulsesink")
-                    .name("tytimer-pa-sink")
-                    .build()
-            })
-            .or_else(|_| {
-                gstreamer::ElementFactory::make("autoaudiosink")
-                    .name("tytimer-auto-sink")
-                    .build()
-            });
+        let _ = pipeline.set_state(gstreamer::State::Playing);
+        *self.cue.borrow_mut() = Some(pipeline);
+    }
 
-        let audio_sink = match audio_sink {
-            Ok(sink) => sink,
-            Err(_) => return,
+    fn stop_cue(&self) {
+        if let Some(pipeline) = self.cue.borrow_mut().take() {
+            let _ = pipeline.set_state(gstreamer::State::Null);
+        }
+    }
+
+    pub fn play_alarm(&self, options: &AlarmOptions) {
+        self.stop();
+        let ring = Ring {
+            sound: Rc::new(load_sound(options.sound.as_deref())),
+            repeat: options.repeat,
+            ramp: options
+                .ramp
+                .filter(|secs| *secs > 0)
+                .map(|secs| (Instant::now(), Duration::from_secs(secs as u64))),
         };
+        play(&self.player, ring);
+    }
 
-        // Add elements to pipeline
-        if pipeline
-            .add_many([&appsrc, &decodebin, &audio_sink])
-            .is_err()
-        {
-            return;
-        }
+    pub fn stop(&self) {
+        self.player.borrow_mut().take();
+        self.stop_cue();
+    }
+}
 
-        // Link appsrc to decodebin
-        if appsrc.link(&decodebin).is_err() {
-            return;
-        }
+/// What an alarm plays, kept so each repeat can start it afresh.
+#[derive(Clone)]
+struct Ring {
+    sound: Rc<Cow<'static, [u8]>>,
+    repeat: bool,
+    /// When the volume ramp began and how long it lasts.
+    ramp: Option<(Instant, Duration)>,
+}
 
-        // Configure appsrc
-        let appsrc = appsrc.dynamic_cast::<gstreamer_app::AppSrc>().unwrap();
-        appsrc.set_property("format", gstreamer::Format::Bytes);
-        appsrc.set_property("is-live", false);
-
-        // Connect decodebin pad-added signal to link to audio sink
-        let audio_sink_weak = audio_sink.downgrade();
-        decodebin.connect_pad_added(move |_, src_pad| {
-            if let Some(sink) = audio_sink_weak.upgrade() {
-                let sink_pad = sink.static_pad("sink").unwrap();
-                if !sink_pad.is_linked() {
-                    let _ = src_pad.link(&sink_pad);
-                }
-            }
-        });
+/// Play `ring` into `slot` on a new pipeline.
+///
+/// The source is a one-shot stream that cannot seek, and resetting a pipeline
+/// leaves its appsrc flushing so anything fed to it is dropped. Each repeat,
+/// and the fallback to the built-in sound, therefore builds a pipeline of its
+/// own.
+fn play(slot: &Rc<RefCell<Option<Playback>>>, ring: Ring) {
+    slot.borrow_mut().take();
 
-        // Feed the embedded MP3 data to appsrc
-        let sound_data = ALARM_SOUND.to_vec();
-        let mut buffer = gstreamer::Buffer::with_size(sound_data.len()).unwrap();
-        {
-            let buffer_ref = buffer.get_mut().unwrap();
-            buffer_ref.copy_from_slice(0, &sound_data).unwrap();
+    // Create a pipeline with appsrc for memory playback
+    let pipeline = gstreamer::Pipeline::new();
+
+    let appsrc = match gstreamer::ElementFactory::make("appsrc")
+        .name("tytimer-src")
+        .build()
+    {
+        Ok(element) => element,
+        Err(_) => return,
+    };
+
+    let decodebin = match gstreamer::ElementFactory::make("decodebin")
+        .name("tytimer-decoder")
+        .build()
+    {
+        Ok(element) => element,
+        Err(_) => return,
+    };
+
+    // Converter and volume stage, used for the ramp.
+    let (convert, volume) = match (
+        gstreamer::ElementFactory::make("audioconvert")
+            .name("tytimer-convert")
+            .build(),
+        gstreamer::ElementFactory::make("volume")
+            .name("tytimer-volume")
+            .build(),
+    ) {
+        (Ok(convert), Ok(volume)) => (convert, volume),
+        _ => return,
+    };
+
+    let Some(audio_sink) = make_sink() else {
+        return;
+    };
+
+    // Add elements to pipeline
+    if pipeline
+        .add_many([&appsrc, &decodebin, &convert, &volume, &audio_sink])
+        .is_err()
+    {
+        return;
+    }
+
+    // Link appsrc to decodebin, and the volume stage to the sink
+    if appsrc.link(&decodebin).is_err()
+        || gstreamer::Element::link_many([&convert, &volume, &audio_sink]).is_err()
+    {
+        return;
+    }
+
+    // Configure appsrc
+    let appsrc = appsrc.dynamic_cast::<gstreamer_app::AppSrc>().unwrap();
+    appsrc.set_property("format", gstreamer::Format::Bytes);
+    appsrc.set_property("is-live", false);
+
+    // Connect decodebin pad-added signal to link to the volume stage
+    let convert_weak = convert.downgrade();
+    decodebin.connect_pad_added(move |_, src_pad| {
+        if let Some(convert) = convert_weak.upgrade() {
+            let sink_pad = convert.static_pad("sink").unwrap();
+            if !sink_pad.is_linked() {
+                let _ = src_pad.link(&sink_pad);
+            }
         }
+    });
 
-        let _ = appsrc.push_buffer(buffer);
-        let _ = appsrc.end_of_stream();
-
-        // Set up bus message handling
-        if let Some(bus) = pipeline.bus() {
-            let pipeline_weak = pipeline.downgrade();
-            let _ = bus.add_watch_local(move |_, msg| {
-                use gstreamer::MessageView;
-                match msg.view() {
-                    MessageView::Eos(_) => {
-                        if let Some(pipeline) = pipeline_weak.upgrade() {
-                            let _ = pipeline.set_state(gstreamer::State::Null);
-                        }
-                        glib::ControlFlow::Break
-                    }
-                    MessageView::Error(err) => {
-                        eprintln!("GStreamer error: {:?}", err.error());
-                        if let Some(pipeline) = pipeline_weak.upgrade() {
-                            let _ = pipeline.set_state(gstreamer::State::Null);
-                        }
-                        glib::ControlFlow::Break
+    feed(&appsrc, &ring.sound);
+
+    // Set up bus message handling
+    let Some(bus) = pipeline.bus() else {
+        return;
+    };
+    let pipeline_weak = pipeline.downgrade();
+    let slot_weak = Rc::downgrade(slot);
+    let ramp = ring.ramp;
+    // The watch stays installed until `Playback` (which owns its guard) is dropped.
+    let watch = bus.add_watch_local(move |_, msg| {
+        use gstreamer::MessageView;
+        let Some(pipeline) = pipeline_weak.upgrade() else {
+            return glib::ControlFlow::Break;
+        };
+        let next = match msg.view() {
+            MessageView::Eos(_) if ring.repeat => Some(ring.clone()),
+            MessageView::Eos(_) => None,
+            MessageView::Error(err) => {
+                eprintln!("GStreamer error: {:?}", err.error());
+                // A custom sound that fails to decode falls back to the embedded one.
+                matches!(*ring.sound, Cow::Owned(_)).then(|| {
+                    eprintln!("Falling back to the built-in alarm sound");
+                    Ring {
+                        sound: Rc::new(Cow::Borrowed(ALARM_SOUND)),
+                        ..ring.clone()
                     }
-                    _ => glib::ControlFlow::Continue,
+                })
+            }
+            _ => return glib::ControlFlow::Continue,
+        };
+        let _ = pipeline.set_state(gstreamer::State::Null);
+        if let Some(next) = next {
+            // Out of this watch before its pipeline is replaced.
+            let slot_weak = slot_weak.clone();
+            glib::idle_add_local_once(move || {
+                let Some(slot) = slot_weak.upgrade() else {
+                    return;
+                };
+                // Unless the alarm was stopped meanwhile.
+                let current = slot
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|playback| playback.pipeline == pipeline);
+                if current {
+                    play(&slot, next);
                 }
             });
         }
+        glib::ControlFlow::Continue
+    });
+    let watch = match watch {
+        Ok(watch) => watch,
+        Err(err) => {
+            eprintln!("Failed to watch the alarm pipeline: {err}");
+            return;
+        }
+    };
 
-        let _ = pipeline.set_state(gstreamer::State::Playing);
-        *self.player.borrow_mut() = Some(pipeline.upcast());
+    if let Some((started, ramp)) = ramp {
+        start_ramp(&volume, started, ramp);
     }
 
-    pub fn stop(&self) {
-        if let Some(player) = self.player.borrow_mut().take() {
-            let _ = player.set_state(gstreamer::State::Null);
+    let _ = pipeline.set_state(gstreamer::State::Playing);
+    *slot.borrow_mut() = Some(Playback {
+        pipeline,
+        _watch: watch,
+    });
+}
+
+/// The first audio output available: PipeWire, then Pulse, then whatever
+/// GStreamer picks.
+fn make_sink() -> Option<gstreamer::Element> {
+    gstreamer::ElementFactory::make("pipewiresink")
+        .build()
+        .or_else(|_| gstreamer::ElementFactory::make("pulsesink").build())
+        .or_else(|_| gstreamer::ElementFactory::make("autoaudiosink").build())
+        .ok()
+}
+
+/// The contents of `path`, or the embedded sound when there is no usable file.
+fn load_sound(path: Option<&Path>) -> Cow<'static, [u8]> {
+    let Some(path) = path else {
+        return Cow::Borrowed(ALARM_SOUND);
+    };
+    match std::fs::read(path) {
+        Ok(data) if !data.is_empty() => Cow::Owned(data),
+        Ok(_) => {
+            eprintln!("Alarm sound {} is empty, using the built-in one", path.display());
+            Cow::Borrowed(ALARM_SOUND)
+        }
+        Err(err) => {
+            eprintln!(
+                "Cannot read alarm sound {}: {err}; using the built-in one",
+                path.display()
+            );
+            Cow::Borrowed(ALARM_SOUND)
         }
     }
 }
+
+/// Push `sound` into `appsrc` as one buffer followed by end-of-stream.
+fn feed(appsrc: &gstreamer_app::AppSrc, sound: &[u8]) {
+    let mut buffer = gstreamer::Buffer::with_size(sound.len()).unwrap();
+    {
+        let buffer_ref = buffer.get_mut().unwrap();
+        buffer_ref.copy_from_slice(0, sound).unwrap();
+    }
+
+    let _ = appsrc.push_buffer(buffer);
+    let _ = appsrc.end_of_stream();
+}
+
+/// Raise `volume` from `RAMP_START` to full over `ramp` from `started`, so a
+/// repeat carries on where the last play left off. Stops on its own once the
+/// ramp is done or the pipeline is gone.
+fn start_ramp(volume: &gstreamer::Element, started: Instant, ramp: Duration) {
+    let level = move || {
+        let progress = (started.elapsed().as_secs_f64() / ramp.as_secs_f64()).min(1.0);
+        (RAMP_START + (1.0 - RAMP_START) * progress, progress)
+    };
+    volume.set_property("volume", level().0);
+    let volume = volume.downgrade();
+    glib::timeout_add_local(Duration::from_millis(100), move || {
+        let Some(volume) = volume.upgrade() else {
+            return glib::ControlFlow::Break;
+        };
+        let (level, progress) = level();
+        volume.set_property("volume", level);
+        if progress < 1.0 {
+            glib::ControlFlow::Continue
+        } else {
+            glib::ControlFlow::Break
+        }
+    });
+}
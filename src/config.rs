@@ -0,0 +1,521 @@
+use crate::audio::AlarmOptions;
+use crate::duration;
+use crate::warning::Warning;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Settings from `$XDG_CONFIG_HOME/tytimer/config.toml`. Every key is
+/// optional; anything left out keeps the built-in default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// What the setter window starts at.
+    #[serde(deserialize_with = "seconds")]
+    pub default_duration: i64,
+    /// The snooze buttons in the alarm window, e.g. `["1%", "5%", "5m"]`.
+    pub snooze: Vec<Snooze>,
+    pub window: WindowConfig,
+    pub setter: SetterConfig,
+    pub alarm: AlarmConfig,
+    pub theme: ThemeConfig,
+    pub tray: TrayConfig,
+    pub pomodoro: PomodoroConfig,
+    /// Named timers for `tytimer <name>`, e.g. `[presets.tea]`.
+    pub presets: BTreeMap<String, Preset>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_duration: 5 * 60,
+            snooze: vec![Snooze::Percent(1.0), Snooze::Percent(5.0), Snooze::Percent(10.0)],
+            window: WindowConfig::default(),
+            setter: SetterConfig::default(),
+            alarm: AlarmConfig::default(),
+            theme: ThemeConfig::default(),
+            tray: TrayConfig::default(),
+            pomodoro: PomodoroConfig::default(),
+            presets: BTreeMap::new(),
+        }
+    }
+}
+
+/// One snooze button: a share of the timer's original length, or a fixed time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Snooze {
+    Percent(f64),
+    Seconds(i64),
+}
+
+impl Snooze {
+    /// How long to snooze a timer that originally ran for `original` seconds.
+    pub fn seconds(self, original: i64) -> i64 {
+        match self {
+            Snooze::Percent(percent) => ((original as f64) * percent / 100.0).round() as i64,
+            Snooze::Seconds(seconds) => seconds,
+        }
+        .max(1)
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let Some(percent) = text.trim().strip_suffix('%') else {
+            return Ok(Snooze::Seconds(duration::parse(text)?));
+        };
+        match percent.trim().parse::<f64>() {
+            Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(Snooze::Percent(percent)),
+            Ok(_) => bail!("`{text}` must be above 0% and at most 100%"),
+            Err(_) => bail!("`{text}` is not a percentage"),
+        }
+    }
+}
+
+impl fmt::Display for Snooze {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Snooze::Percent(percent) => write!(f, "{percent}%"),
+            Snooze::Seconds(seconds) => f.write_str(&duration::format(*seconds)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Snooze {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Snooze::parse(&text).map_err(de::Error::custom)
+    }
+}
+
+/// A named timer that is started often, like `tea` or `standup`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    #[serde(deserialize_with = "seconds")]
+    pub duration: i64,
+    /// Shown in the tray and window; defaults to the preset's name.
+    pub label: Option<String>,
+    pub message: Option<String>,
+    /// Overrides `alarm.sound` for this preset.
+    pub sound: Option<PathBuf>,
+}
+
+/// Where the timer and setter windows sit on screen.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub anchor: Anchor,
+    /// Distance from the anchored screen edges, in pixels.
+    pub margin: i32,
+    pub layer: WindowLayer,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            anchor: Anchor::TopRight,
+            margin: 16,
+            layer: WindowLayer::Top,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowLayer {
+    Top,
+    Overlay,
+}
+
+/// The setter window's slider.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SetterConfig {
+    #[serde(deserialize_with = "seconds")]
+    pub min: i64,
+    #[serde(deserialize_with = "seconds")]
+    pub max: i64,
+    #[serde(deserialize_with = "seconds")]
+    pub step: i64,
+}
+
+impl Default for SetterConfig {
+    fn default() -> Self {
+        Self {
+            min: 30,
+            max: 120 * 60,
+            step: 30,
+        }
+    }
+}
+
+/// Defaults for how alarms ring; the matching command-line options win.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlarmConfig {
+    pub sound: Option<PathBuf>,
+    pub repeat: bool,
+    #[serde(deserialize_with = "optional_seconds")]
+    pub ramp: Option<i64>,
+    #[serde(deserialize_with = "optional_seconds")]
+    pub ring_for: Option<i64>,
+    #[serde(deserialize_with = "optional_seconds")]
+    pub realert: Option<i64>,
+    pub notify: bool,
+    /// e.g. `["5m", "50%"]`
+    pub warn: Vec<Warning>,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        let options = AlarmOptions::default();
+        Self {
+            sound: options.sound,
+            repeat: options.repeat,
+            ramp: options.ramp,
+            ring_for: options.ring_for,
+            realert: options.realert,
+            notify: options.notify,
+            warn: options.warn,
+        }
+    }
+}
+
+impl AlarmConfig {
+    pub fn options(&self) -> AlarmOptions {
+        AlarmOptions {
+            sound: self.sound.as_deref().map(expand_home),
+            repeat: self.repeat,
+            ramp: self.ramp,
+            ring_for: self.ring_for,
+            realert: self.realert,
+            notify: self.notify,
+            warn: self.warn.clone(),
+        }
+    }
+}
+
+/// Extra styling, applied on top of the built-in look.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// GTK CSS to add, e.g. `window { background-color: #202020; }`.
+    pub css: Option<String>,
+    /// A file of GTK CSS to add.
+    pub css_file: Option<PathBuf>,
+}
+
+impl ThemeConfig {
+    /// All configured CSS, inline rules last so they win.
+    pub fn css(&self) -> String {
+        let mut css = String::new();
+        if let Some(path) = &self.css_file {
+            match std::fs::read_to_string(expand_home(path)) {
+                Ok(text) => css.push_str(&text),
+                Err(err) => eprintln!("Cannot read theme {}: {err}", path.display()),
+            }
+        }
+        if let Some(extra) = &self.css {
+            css.push('\n');
+            css.push_str(extra);
+        }
+        css
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrayConfig {
+    /// Show tray icons at all.
+    pub enabled: bool,
+    /// One icon per timer, or one for all of them.
+    pub mode: TrayMode,
+    /// What clicking the tray icon does.
+    pub click: TrayClick,
+    /// Draw the icon as a ring showing the time left, instead of a themed icon.
+    pub progress_icon: bool,
+    /// Show the minutes left in the middle of the ring.
+    pub digits: bool,
+    /// Show a countdown pill on screen while no tray host is running.
+    pub fallback: bool,
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: TrayMode::PerTimer,
+            click: TrayClick::ShowWindow,
+            progress_icon: true,
+            digits: false,
+            fallback: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrayMode {
+    PerTimer,
+    /// A single icon showing the soonest timer, with every timer in its menu.
+    Aggregated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrayClick {
+    ShowWindow,
+    TogglePause,
+}
+
+/// Defaults for `tytimer pomodoro`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroConfig {
+    #[serde(deserialize_with = "seconds")]
+    pub work: i64,
+    #[serde(deserialize_with = "seconds")]
+    pub short_break: i64,
+    #[serde(deserialize_with = "seconds")]
+    pub long_break: i64,
+    /// Work phases between long breaks.
+    pub long_every: u32,
+    /// Sounds announcing each phase; unset ones use `alarm.sound`.
+    pub work_sound: Option<PathBuf>,
+    pub break_sound: Option<PathBuf>,
+    pub long_break_sound: Option<PathBuf>,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: 25 * 60,
+            short_break: 5 * 60,
+            long_break: 15 * 60,
+            long_every: 4,
+            work_sound: None,
+            break_sound: None,
+            long_break_sound: None,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/tytimer/config.toml`, falling back to `~/.config`.
+pub fn path() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("tytimer").join("config.toml")
+}
+
+/// Read and check the config file. A missing file is not an error, and
+/// settings naming files that are not there fall back to the built-in ones
+/// with a warning.
+pub fn load() -> Result<Config> {
+    let path = path();
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+    let mut config = parse(&text).map_err(|err| anyhow!("{}: {err}", path.display()))?;
+    if let Some(problem) = config.problems().first() {
+        bail!("{}: {problem}", path.display());
+    }
+    for warning in config.drop_unusable() {
+        eprintln!("{}: {warning}", path.display());
+    }
+    Ok(config)
+}
+
+/// The config to run with: the file's if it is valid, otherwise the
+/// defaults after reporting why.
+pub fn load_or_default() -> Config {
+    load().unwrap_or_else(|err| {
+        eprintln!("Ignoring config: {err}");
+        Config::default()
+    })
+}
+
+/// `tytimer config check`: report every problem in the config file.
+/// `commands` are the words `tytimer` takes as subcommands.
+pub fn check(commands: &[String]) -> Result<()> {
+    let path = path();
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            println!("No config file at {}; using defaults.", path.display());
+            return Ok(());
+        }
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+    let mut config = parse(&text).map_err(|err| anyhow!("{}: {err}", path.display()))?;
+    let mut warnings = config.drop_unusable();
+    warnings.extend(config.drop_shadowed_presets(commands));
+    for warning in warnings {
+        eprintln!("{}: warning: {warning}", path.display());
+    }
+    let problems = config.problems();
+    if problems.is_empty() {
+        println!("✅ {} is valid.", path.display());
+        return Ok(());
+    }
+    for problem in &problems {
+        eprintln!("{}: {problem}", path.display());
+    }
+    bail!("{} problem(s) found", problems.len())
+}
+
+fn parse(text: &str) -> Result<Config> {
+    // The TOML error already points at the offending line and column.
+    Ok(toml::from_str(text)?)
+}
+
+impl Config {
+    /// Checks that need more than one key at a time; any of these makes the
+    /// file unusable.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let setter = &self.setter;
+        if setter.min >= setter.max {
+            problems.push(format!(
+                "setter.min ({}) must be less than setter.max ({})",
+                duration::format(setter.min),
+                duration::format(setter.max)
+            ));
+        }
+        if setter.step > setter.max - setter.min {
+            problems.push("setter.step is larger than the setter's range".into());
+        }
+        if !(setter.min..=setter.max).contains(&self.default_duration) {
+            problems.push(format!(
+                "default_duration ({}) is outside the setter range {}–{}",
+                duration::format(self.default_duration),
+                duration::format(setter.min),
+                duration::format(setter.max)
+            ));
+        }
+        if self.snooze.is_empty() {
+            problems.push("snooze needs at least one entry".into());
+        }
+        if self.pomodoro.long_every == 0 {
+            problems.push("pomodoro.long_every must be at least 1".into());
+        }
+        if self.window.margin < 0 {
+            problems.push(format!("window.margin ({}) cannot be negative", self.window.margin));
+        }
+        problems
+    }
+
+    /// Drop the presets that `tytimer <name>` would take for one of
+    /// `commands` instead, saying which.
+    pub fn drop_shadowed_presets(&mut self, commands: &[String]) -> Vec<String> {
+        self.drop_presets(|name| {
+            commands
+                .contains(name)
+                .then(|| format!("`tytimer {name}` runs the {name} command"))
+        })
+    }
+
+    /// Drop the presets `unusable` gives a reason for, as warnings.
+    fn drop_presets(&mut self, unusable: impl Fn(&String) -> Option<String>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        self.presets.retain(|name, _| match unusable(name) {
+            Some(why) => {
+                warnings.push(format!("preset `{name}` is ignored: {why}; pick another name"));
+                false
+            }
+            None => true,
+        });
+        warnings
+    }
+
+    /// Leave out what cannot be used, so the rest of the file still applies,
+    /// and say what was left out: file settings whose file does not exist
+    /// fall back to their defaults, and presets named like a duration are
+    /// dropped.
+    fn drop_unusable(&mut self) -> Vec<String> {
+        let mut warnings = self.drop_presets(|name| {
+            duration::parse(name)
+                .is_ok()
+                .then(|| "it reads as a duration".to_string())
+        });
+
+        let mut files = vec![
+            ("alarm.sound".to_string(), &mut self.alarm.sound),
+            ("theme.css_file".to_string(), &mut self.theme.css_file),
+            ("pomodoro.work_sound".to_string(), &mut self.pomodoro.work_sound),
+            ("pomodoro.break_sound".to_string(), &mut self.pomodoro.break_sound),
+            ("pomodoro.long_break_sound".to_string(), &mut self.pomodoro.long_break_sound),
+        ];
+        files.extend(
+            self.presets
+                .iter_mut()
+                .map(|(name, preset)| (format!("presets.{name}.sound"), &mut preset.sound)),
+        );
+        for (key, path) in files {
+            let Some(full) = path.as_deref().map(expand_home) else {
+                continue;
+            };
+            if !full.is_file() {
+                warnings.push(format!("{key}: {} does not exist; using the default", full.display()));
+                *path = None;
+            }
+        }
+        warnings
+    }
+}
+
+/// Resolve a leading `~/` against `$HOME`.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A duration written as a string (`"90s"`, `"1h30m"`) or, as on the command
+/// line, a bare number of minutes (`25`, `2.5`).
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    struct Visitor;
+
+    impl de::Visitor<'_> for Visitor {
+        type Value = i64;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a duration like \"25m\" or a number of minutes")
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<i64, E> {
+            duration::parse(text).map_err(E::custom)
+        }
+
+        fn visit_i64<E: de::Error>(self, minutes: i64) -> Result<i64, E> {
+            self.visit_str(&minutes.to_string())
+        }
+
+        fn visit_f64<E: de::Error>(self, minutes: f64) -> Result<i64, E> {
+            self.visit_str(&minutes.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(Visitor)
+}
+
+fn optional_seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    seconds(deserializer).map(Some)
+}
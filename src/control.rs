@@ -0,0 +1,116 @@
+use crate::app::format_time;
+use crate::ipc::{self, Command, Request, Response, Target, TimerInfo};
+use crate::wallclock;
+use anyhow::{bail, Result};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// `tytimer list`: print every timer running in any tytimer process.
+pub fn list(json: bool) -> Result<()> {
+    let mut timers = collect_timers();
+    timers.sort_by_key(|timer| timer.remaining);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&timers)?);
+        return Ok(());
+    }
+    if timers.is_empty() {
+        println!("No timers running.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<16} {:>10} {:<8} {:<10}",
+        "ID", "LABEL", "REMAINING", "STATE", "ENDS"
+    );
+    for timer in &timers {
+        let ends = timer
+            .ends_at
+            .map(|secs| wallclock::format_local(UNIX_EPOCH + Duration::from_secs(secs)))
+            .unwrap_or_else(|| "-".into());
+        println!(
+            "{:<12} {:<16} {:>10} {:<8} {:<10}",
+            timer.id,
+            timer.label.as_deref().unwrap_or("-"),
+            format_time(timer.remaining),
+            state_name(timer),
+            ends
+        );
+    }
+    Ok(())
+}
+
+/// `tytimer pause|resume|stop|extend`: apply `command` to the matching timers.
+pub fn control(target: Target, command: Command, json: bool) -> Result<()> {
+    let request = Request::Control {
+        target: target.clone(),
+        command,
+    };
+    let mut affected = Vec::new();
+    let mut laps = Vec::new();
+    let mut refused = false;
+    for response in ipc::broadcast(&request) {
+        match response {
+            Ok(Response::Done {
+                affected: ids,
+                laps: files,
+            }) => {
+                affected.extend(ids);
+                laps.extend(files);
+            }
+            Ok(Response::Error { message }) => {
+                eprintln!("A timer refused the request: {message}");
+                refused = true;
+            }
+            Ok(Response::Timers { .. } | Response::Started { .. }) => {}
+            Err(err) => eprintln!("Could not reach a timer: {err}"),
+        }
+    }
+
+    if affected.is_empty() {
+        match target {
+            _ if refused => bail!("no timer was changed"),
+            Target::All => bail!("no timers running"),
+            Target::Match(name) => bail!("no running timer matches `{name}`"),
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&affected)?);
+    } else {
+        let verb = match command {
+            Command::Pause => "Paused",
+            Command::Resume => "Resumed",
+            Command::Stop => "Stopped",
+            Command::Extend { .. } => "Extended",
+        };
+        for id in &affected {
+            println!("{verb} {id}");
+        }
+        for path in &laps {
+            println!("Laps saved to {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn collect_timers() -> Vec<TimerInfo> {
+    let mut timers = Vec::new();
+    for response in ipc::broadcast(&Request::List) {
+        match response {
+            Ok(Response::Timers { timers: found }) => timers.extend(found),
+            Ok(_) => {}
+            Err(err) => eprintln!("Could not reach a timer: {err}"),
+        }
+    }
+    timers
+}
+
+fn state_name(timer: &TimerInfo) -> &'static str {
+    if timer.alarm {
+        "ringing"
+    } else if timer.running {
+        "running"
+    } else {
+        "paused"
+    }
+}
@@ -0,0 +1,64 @@
+use crate::host::Host;
+use crate::ipc::{self, IpcServer, Request, Response};
+use crate::persist;
+use crate::schedule;
+use crate::timer::TimerSpec;
+use anyhow::{anyhow, bail, Result};
+use gtk4::gio::ApplicationFlags;
+use gtk4::prelude::*;
+
+/// `tytimer daemon`: host every new timer in this one process.
+pub fn run() -> Result<()> {
+    let socket = ipc::daemon_socket();
+    if ipc::request(&socket, &Request::List).is_ok() {
+        bail!("a tytimer daemon is already running");
+    }
+    let (server, calls) = IpcServer::spawn(socket)?;
+
+    let app = gtk4::Application::builder()
+        .application_id("dev.ty.timers.daemon")
+        .flags(ApplicationFlags::NON_UNIQUE)
+        .build();
+
+    let calls = std::cell::RefCell::new(Some(calls));
+    app.connect_activate(move |gtk_app| {
+        let Some(calls) = calls.borrow_mut().take() else {
+            return;
+        };
+        // Keep running with no timers (and so no windows) around.
+        std::mem::forget(gtk_app.hold());
+        let host = Host::new(gtk_app, true);
+        for spec in persist::claim_orphans() {
+            if let Err(err) = host.start(&spec) {
+                eprintln!("Failed to restore timer: {err}");
+            }
+        }
+        host.serve(calls);
+        schedule::run(&host);
+    });
+
+    // Quit cleanly on SIGTERM/SIGINT so the socket file is removed.
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        let app = app.clone();
+        glib::unix_signal_add_local(signal, move || {
+            app.quit();
+            glib::ControlFlow::Break
+        });
+    }
+
+    app.run_with_args(&Vec::<&str>::new());
+    drop(server);
+    Ok(())
+}
+
+/// Hand `spec` to a running daemon, returning the new timer's id.
+pub fn hand_off(spec: &TimerSpec) -> Result<String> {
+    let request = Request::Start {
+        spec: Box::new(spec.clone()),
+    };
+    match ipc::request(&ipc::daemon_socket(), &request)? {
+        Response::Started { id } => Ok(id),
+        Response::Error { message } => Err(anyhow!(message)),
+        other => Err(anyhow!("unexpected answer from daemon: {other:?}")),
+    }
+}
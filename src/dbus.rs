@@ -0,0 +1,227 @@
+use crate::app::TimerApp;
+use crate::duration;
+use crate::ipc::TimerInfo;
+use crate::tray::TrayAction;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use zbus::blocking::{connection, Connection};
+use zbus::fdo::ObjectManager;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Value;
+
+const INTERFACE: &str = "dev.ty.timers.Timer";
+const ROOT_PATH: &str = "/dev/ty/timers";
+
+/// This process's session bus connection, exporting one object per timer.
+///
+/// Each process owns `dev.ty.timers.Pid<pid>` and an object manager at
+/// `/dev/ty/timers`, so clients can find every timer with `GetManagedObjects`.
+pub struct DbusService {
+    conn: Connection,
+}
+
+impl DbusService {
+    pub fn connect() -> Result<Self> {
+        // Serve the object manager through the builder: with zbus's tokio
+        // backend the object server has to be set up inside its runtime.
+        let conn = connection::Builder::session()?
+            .name(format!("dev.ty.timers.Pid{}", std::process::id()))?
+            .serve_at(ROOT_PATH, ObjectManager)?
+            .build()?;
+        Ok(Self { conn })
+    }
+
+    /// Export the timer `info.id` at `/dev/ty/timers/t<id>` (dashes become
+    /// underscores); method calls are delivered as `TrayAction`s on `action_tx`.
+    pub fn register(&self, info: TimerInfo, action_tx: Sender<TrayAction>) -> Result<DbusTimer> {
+        let path = format!("{ROOT_PATH}/{}", object_name(&info.id));
+        let shared = Arc::new(Mutex::new(info));
+        self.conn.object_server().at(
+            path.as_str(),
+            TimerObject {
+                info: Arc::clone(&shared),
+                action_tx,
+            },
+        )?;
+        Ok(DbusTimer {
+            conn: self.conn.clone(),
+            path,
+            info: shared,
+        })
+    }
+}
+
+/// Handle to one exported timer, used to publish its state changes.
+pub struct DbusTimer {
+    conn: Connection,
+    path: String,
+    info: Arc<Mutex<TimerInfo>>,
+}
+
+impl DbusTimer {
+    /// Publish a fresh snapshot, emitting `PropertiesChanged` for what differs
+    /// and `Tick` whenever the remaining seconds move.
+    pub fn update(&self, info: TimerInfo) {
+        let mut current = self.info.lock().unwrap();
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if current.remaining != info.remaining {
+            changed.insert("Remaining", info.remaining.into());
+        }
+        if current.original != info.original {
+            changed.insert("Original", info.original.into());
+        }
+        if current.running != info.running {
+            changed.insert("Running", info.running.into());
+        }
+        if current.alarm != info.alarm {
+            changed.insert("Alarm", info.alarm.into());
+        }
+        let tick = current.remaining != info.remaining;
+        *current = info;
+        let remaining = current.remaining;
+        drop(current);
+
+        if !changed.is_empty() {
+            let invalidated: Vec<&str> = Vec::new();
+            self.emit(
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+                &(INTERFACE, changed, invalidated),
+            );
+        }
+        if tick {
+            self.emit(INTERFACE, "Tick", &(remaining,));
+        }
+    }
+
+    /// Emit `Finished` when the alarm goes off.
+    pub fn finished(&self) {
+        self.emit(INTERFACE, "Finished", &());
+    }
+
+    fn emit<B>(&self, interface: &str, signal: &str, body: &B)
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        if let Err(err) =
+            self.conn
+                .emit_signal(None::<&str>, self.path.as_str(), interface, signal, body)
+        {
+            eprintln!("Failed to emit D-Bus {signal}: {err}");
+        }
+    }
+}
+
+impl Drop for DbusTimer {
+    fn drop(&mut self) {
+        let _ = self
+            .conn
+            .object_server()
+            .remove::<TimerObject, _>(self.path.as_str());
+    }
+}
+
+struct TimerObject {
+    info: Arc<Mutex<TimerInfo>>,
+    action_tx: Sender<TrayAction>,
+}
+
+impl TimerObject {
+    fn send(&self, action: TrayAction) -> zbus::fdo::Result<()> {
+        TimerApp::check(&self.info.lock().unwrap(), action)
+            .map_err(|refusal| zbus::fdo::Error::NotSupported(refusal.to_string()))?;
+        self.action_tx
+            .send(action)
+            .map_err(|_| zbus::fdo::Error::Failed("timer has stopped".into()))
+    }
+}
+
+#[zbus::interface(name = "dev.ty.timers.Timer")]
+impl TimerObject {
+    fn pause(&self) -> zbus::fdo::Result<()> {
+        self.send(TrayAction::Pause)
+    }
+
+    fn resume(&self) -> zbus::fdo::Result<()> {
+        self.send(TrayAction::Resume)
+    }
+
+    fn stop(&self) -> zbus::fdo::Result<()> {
+        self.send(TrayAction::Quit)
+    }
+
+    /// Move the end by `seconds` (negative to shorten).
+    fn extend(&self, seconds: i64) -> zbus::fdo::Result<()> {
+        let seconds = duration::check(seconds)
+            .map_err(|err| zbus::fdo::Error::InvalidArgs(err.to_string()))?;
+        self.send(TrayAction::Extend(seconds))
+    }
+
+    /// Start the next phase of a pomodoro session.
+    fn skip_phase(&self) -> zbus::fdo::Result<()> {
+        self.send(TrayAction::SkipPhase)
+    }
+
+    /// Record a lap on a stopwatch.
+    fn lap(&self) -> zbus::fdo::Result<()> {
+        self.send(TrayAction::Lap)
+    }
+
+    /// Silence the alarm and ring again in `seconds`.
+    fn snooze(&self, seconds: i64) -> zbus::fdo::Result<()> {
+        if seconds <= 0 {
+            return Err(zbus::fdo::Error::InvalidArgs("snooze must be positive".into()));
+        }
+        let seconds = duration::check(seconds)
+            .map_err(|err| zbus::fdo::Error::InvalidArgs(err.to_string()))?;
+        self.send(TrayAction::Snooze(seconds))
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> String {
+        self.info.lock().unwrap().id.clone()
+    }
+
+    /// The timer's label, or an empty string.
+    #[zbus(property)]
+    fn label(&self) -> String {
+        self.info.lock().unwrap().label.clone().unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    fn remaining(&self) -> i64 {
+        self.info.lock().unwrap().remaining
+    }
+
+    #[zbus(property)]
+    fn original(&self) -> i64 {
+        self.info.lock().unwrap().original
+    }
+
+    #[zbus(property)]
+    fn running(&self) -> bool {
+        self.info.lock().unwrap().running
+    }
+
+    #[zbus(property)]
+    fn alarm(&self) -> bool {
+        self.info.lock().unwrap().alarm
+    }
+
+    #[zbus(signal)]
+    async fn tick(emitter: &SignalEmitter<'_>, remaining: i64) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn finished(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// D-Bus object path elements may only contain `[A-Za-z0-9_]`.
+fn object_name(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("t{name}")
+}
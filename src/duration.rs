@@ -0,0 +1,235 @@
+use anyhow::{anyhow, bail, Result};
+
+/// Longest timer we accept: one week.
+pub const MAX_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Parse a human-friendly duration into whole seconds.
+///
+/// Accepted forms:
+/// - a bare number, read as minutes: `45`, `2.5`
+/// - unit components in any combination: `1h30m`, `90s`, `2.5m`, `1h 5m 30s`
+/// - clock notation: `1:30:00` (h:mm:ss) or `4:30` (m:ss)
+pub fn parse(input: &str) -> Result<i64> {
+    parse_allowing_zero(input, false)
+}
+
+/// Like [`parse`], but also accepts a zero length (`0`, `0s`, `0:00`), for
+/// settings where nothing at all is meaningful.
+pub fn parse_or_zero(input: &str) -> Result<i64> {
+    parse_allowing_zero(input, true)
+}
+
+fn parse_allowing_zero(input: &str, zero: bool) -> Result<i64> {
+    let text = input.trim();
+    if text.is_empty() {
+        bail!("duration is empty");
+    }
+    if text.starts_with('-') {
+        bail!("duration `{text}` must be positive");
+    }
+
+    let seconds = if text.contains(':') {
+        parse_clock(text)?
+    } else if text.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        parse_number(text, text)? * 60.0
+    } else {
+        parse_units(text)?
+    };
+
+    let seconds = seconds.round();
+    if zero && seconds == 0.0 {
+        return Ok(0);
+    }
+    if seconds < 1.0 {
+        bail!("duration `{text}` is shorter than one second");
+    }
+    if seconds > MAX_SECONDS as f64 {
+        bail!("duration `{text}` is longer than the maximum of 7 days");
+    }
+    Ok(seconds as i64)
+}
+
+/// Check a number of seconds that did not come through [`parse`], such as
+/// an extension sent over D-Bus or the control socket: either way, it can be
+/// no longer than the longest timer.
+pub fn check(seconds: i64) -> Result<i64> {
+    if seconds.unsigned_abs() > MAX_SECONDS as u64 {
+        bail!("{seconds}s is longer than the maximum of 7 days");
+    }
+    Ok(seconds)
+}
+
+/// Render whole seconds in the unit form `parse` accepts: `1h30m`, `5m`, `90s`.
+pub fn format(total_seconds: i64) -> String {
+    let (hours, minutes, seconds) = (
+        total_seconds / 3600,
+        total_seconds % 3600 / 60,
+        total_seconds % 60,
+    );
+    let mut text = String::new();
+    for (value, unit) in [(hours, "h"), (minutes, "m"), (seconds, "s")] {
+        if value != 0 {
+            text.push_str(&format!("{value}{unit}"));
+        }
+    }
+    if text.is_empty() {
+        text.push_str("0s");
+    }
+    text
+}
+
+fn parse_number(number: &str, whole: &str) -> Result<f64> {
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("`{number}` in `{whole}` is not a number"))?;
+    if !value.is_finite() {
+        bail!("`{number}` in `{whole}` is not a finite number");
+    }
+    Ok(value)
+}
+
+fn parse_clock(text: &str) -> Result<f64> {
+    let parts: Vec<&str> = text.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [m, s] => ("0", *m, *s),
+        [h, m, s] => (*h, *m, *s),
+        _ => bail!("`{text}` is not in h:mm:ss or m:ss form"),
+    };
+    for part in [hours, minutes, seconds] {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            bail!("`{text}` is not in h:mm:ss or m:ss form");
+        }
+    }
+    let hours = parse_number(hours, text)?;
+    let minutes = parse_number(minutes, text)?;
+    let seconds = parse_number(seconds, text)?;
+    if seconds >= 60.0 || (parts.len() == 3 && minutes >= 60.0) {
+        bail!("`{text}` has a field out of range (minutes and seconds must be below 60)");
+    }
+    Ok(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+fn parse_units(text: &str) -> Result<f64> {
+    let mut total = 0.0;
+    let mut seen: Vec<&str> = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        if number_len == 0 && rest == text {
+            bail!("`{text}` is not a duration (try `25`, `90s`, `1h30m` or `1:30:00`)");
+        }
+        if number_len == 0 {
+            bail!("expected a number at `{rest}` in `{text}`");
+        }
+        let (number, tail) = rest.split_at(number_len);
+        let tail = tail.trim_start();
+        let unit_len = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        let (name, scale) = match unit.to_ascii_lowercase().as_str() {
+            "h" | "hr" | "hrs" | "hour" | "hours" => ("hours", 3600.0),
+            "m" | "min" | "mins" | "minute" | "minutes" => ("minutes", 60.0),
+            "s" | "sec" | "secs" | "second" | "seconds" => ("seconds", 1.0),
+            "" => bail!("missing unit after `{number}` in `{text}` (use h, m or s)"),
+            other => bail!("unknown unit `{other}` in `{text}` (use h, m or s)"),
+        };
+        if seen.contains(&name) {
+            bail!("{name} given more than once in `{text}`");
+        }
+        seen.push(name);
+
+        total += parse_number(number, text)? * scale;
+        rest = tail.trim_start();
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepted_forms() {
+        for (input, seconds) in [
+            ("45", 45 * 60),
+            ("2.5", 150),
+            (" 25 ", 25 * 60),
+            ("90s", 90),
+            ("2.5m", 150),
+            ("1.6s", 2),
+            ("1h30m", 5400),
+            ("1h 5m 30s", 3930),
+            ("1 hour 30 minutes", 5400),
+            ("2 HRS", 7200),
+            ("30m1h", 5400),
+            ("1:30:00", 5400),
+            ("4:30", 270),
+            ("168h", MAX_SECONDS),
+        ] {
+            assert_eq!(parse(input).unwrap(), seconds, "`{input}`");
+        }
+    }
+
+    #[test]
+    fn zero_only_where_allowed() {
+        for input in ["0", "0s", "0:00", "0m0s", "0.2s"] {
+            assert_eq!(parse_or_zero(input).unwrap(), 0, "`{input}`");
+            assert!(parse(input).is_err(), "`{input}`");
+        }
+        assert_eq!(parse_or_zero("90s").unwrap(), 90);
+    }
+
+    #[test]
+    fn rejects() {
+        for input in [
+            "",
+            "   ",
+            "-5",
+            "-1m",
+            "abc",
+            "nan",
+            "inf",
+            "h",
+            "5x",
+            "5d",
+            "1e400",
+            "1.2.3",
+            "1h 1h",
+            "1h m",
+            "1:60",
+            "1:60:00",
+            "1::00",
+            "1:2:3:4",
+            "1:3x",
+            "169h",
+            "10081",
+            &"9".repeat(400),
+        ] {
+            assert!(parse(input).is_err(), "`{input}` should not parse");
+        }
+    }
+
+    #[test]
+    fn checks_raw_seconds() {
+        assert_eq!(check(-MAX_SECONDS).unwrap(), -MAX_SECONDS);
+        assert_eq!(check(MAX_SECONDS).unwrap(), MAX_SECONDS);
+        for seconds in [MAX_SECONDS + 1, -MAX_SECONDS - 1, i64::MAX, i64::MIN] {
+            assert!(check(seconds).is_err(), "{seconds}");
+        }
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for seconds in [1, 59, 90, 3600, 5400, 3930, MAX_SECONDS] {
+            assert_eq!(parse(&format(seconds)).unwrap(), seconds);
+        }
+        assert_eq!(format(0), "0s");
+        assert_eq!(format(5400), "1h30m");
+    }
+}
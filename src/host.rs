@@ -0,0 +1,173 @@
+use crate::aggregate;
+use crate::app::TimerApp;
+use crate::config::{self, Config, TrayMode};
+use crate::dbus::DbusService;
+use crate::duration;
+use crate::ipc::{Call, Command, Request, Response};
+use crate::notify::Notifier;
+use crate::timer::TimerSpec;
+use crate::tray::TrayAction;
+use anyhow::Result;
+use gtk4::prelude::*;
+use gtk4::Application;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// The timers living in one process, and the services they share.
+///
+/// A standalone process hosts a single timer and quits when it closes; the
+/// daemon hosts any number and keeps running when none are left.
+pub struct Host {
+    app: Application,
+    daemon: bool,
+    dbus: Option<DbusService>,
+    config: Config,
+    /// Connected the first time a timer asks for desktop notifications.
+    notifier: RefCell<Option<Notifier>>,
+    timers: RefCell<Vec<Rc<TimerApp>>>,
+    /// Set while the aggregated tray icon cannot be seen, in that mode.
+    shared_tray: Option<Arc<AtomicBool>>,
+}
+
+impl Host {
+    pub fn new(app: &Application, daemon: bool) -> Rc<Self> {
+        // D-Bus objects for scripts and bars; optional.
+        let dbus = DbusService::connect()
+            .map_err(|err| eprintln!("D-Bus interface failed to start: {err}"))
+            .ok();
+        let config = config::load_or_default();
+        let shared_tray = (config.tray.enabled && config.tray.mode == TrayMode::Aggregated)
+            .then(|| aggregate::spawn(config.tray.clone()));
+
+        Rc::new(Self {
+            app: app.clone(),
+            daemon,
+            dbus,
+            config,
+            notifier: RefCell::new(None),
+            timers: RefCell::new(Vec::new()),
+            shared_tray,
+        })
+    }
+
+    pub fn start(self: &Rc<Self>, spec: &TimerSpec) -> Result<Rc<TimerApp>> {
+        let notifier = if spec.alarm.notify {
+            self.notifier()
+        } else {
+            None
+        };
+        let timer = TimerApp::new(
+            &self.app,
+            spec.clone(),
+            self.dbus.as_ref(),
+            notifier,
+            self.shared_tray.clone(),
+            &self.config,
+        )?;
+        let host = Rc::downgrade(self);
+        timer.connect_closed(move |id| {
+            if let Some(host) = host.upgrade() {
+                host.remove(id);
+            }
+        });
+        timer.present();
+        self.timers.borrow_mut().push(Rc::clone(&timer));
+        Ok(timer)
+    }
+
+    fn notifier(&self) -> Option<Notifier> {
+        let mut notifier = self.notifier.borrow_mut();
+        if notifier.is_none() {
+            *notifier = Notifier::connect()
+                .map_err(|err| eprintln!("Desktop notifications unavailable: {err}"))
+                .ok();
+        }
+        notifier.clone()
+    }
+
+    fn remove(&self, id: &str) {
+        let mut timers = self.timers.borrow_mut();
+        timers.retain(|timer| timer.id() != id);
+        if timers.is_empty() && !self.daemon {
+            self.app.quit();
+        }
+    }
+
+    /// Answer control requests arriving on the process's socket.
+    pub fn serve(self: &Rc<Self>, calls: Receiver<Call>) {
+        let host = Rc::clone(self);
+        glib::timeout_add_local(Duration::from_millis(100), move || {
+            while let Ok(call) = calls.try_recv() {
+                host.handle_call(call);
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+
+    fn handle_call(self: &Rc<Self>, call: Call) {
+        // Work on a copy: stopping a timer removes it from the list.
+        let timers = self.timers.borrow().clone();
+        let response = match &call.request {
+            Request::List => Response::Timers {
+                timers: timers.iter().map(|timer| timer.info()).collect(),
+            },
+            Request::Control { target, command } => {
+                if let Command::Extend { seconds } = command {
+                    if let Err(err) = duration::check(*seconds) {
+                        call.reply(Response::Error {
+                            message: err.to_string(),
+                        });
+                        return;
+                    }
+                }
+                let action = match *command {
+                    Command::Pause => TrayAction::Pause,
+                    Command::Resume => TrayAction::Resume,
+                    Command::Stop => TrayAction::Quit,
+                    Command::Extend { seconds } => TrayAction::Extend(seconds),
+                };
+                let mut matching = Vec::new();
+                let mut refused = Vec::new();
+                for timer in timers.iter().filter(|timer| timer.matches(target)) {
+                    match TimerApp::check(&timer.info(), action) {
+                        Ok(()) => matching.push(timer),
+                        Err(refusal) => refused.push(format!("{}: {refusal}", timer.id())),
+                    }
+                }
+                if matching.is_empty() && !refused.is_empty() {
+                    call.reply(Response::Error {
+                        message: refused.join("; "),
+                    });
+                    return;
+                }
+                let affected = matching.iter().map(|timer| timer.id().to_string()).collect();
+                let laps = match command {
+                    Command::Stop => matching.iter().filter_map(|timer| timer.save_laps()).collect(),
+                    _ => Vec::new(),
+                };
+                // Answer before acting: stopping the last timer ends the process.
+                call.reply(Response::Done { affected, laps });
+                for timer in matching {
+                    timer.handle_action(action);
+                }
+                return;
+            }
+            Request::Start { spec } if self.daemon => match self.start(spec) {
+                Ok(timer) => Response::Started {
+                    id: timer.id().to_string(),
+                },
+                Err(err) => Response::Error {
+                    message: err.to_string(),
+                },
+            },
+            Request::Start { .. } => Response::Error {
+                message: "only the daemon accepts new timers".into(),
+            },
+        };
+        call.reply(response);
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use gtk4::cairo::{Context, FontSlant, FontWeight, Format, ImageSurface, LineCap};
+use ksni::Icon;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::sync::{Arc, Mutex};
+
+/// Pixel sizes rendered for the tray; hosts pick the closest to their bar.
+const SIZES: [i32; 6] = [16, 22, 24, 32, 48, 64];
+
+/// The ring moves in this many steps, so the icon only changes (and hosts
+/// only reload it) when the difference is visible.
+const STEPS: f64 = 90.0;
+
+/// What the progress ring's colour says about the timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Running,
+    /// A `--warn` threshold has passed.
+    Warning,
+    Paused,
+    /// Nearly out of time: the last tenth, in the window.
+    Urgent,
+    /// The alarm went off.
+    Ringing,
+}
+
+impl Tone {
+    fn rgb(self) -> (f64, f64, f64) {
+        match self {
+            Tone::Running => (0.23, 0.51, 0.96),
+            Tone::Warning => (0.96, 0.62, 0.04),
+            Tone::Paused => (0.61, 0.64, 0.69),
+            Tone::Urgent => (0.98, 0.45, 0.09),
+            Tone::Ringing => (0.94, 0.27, 0.27),
+        }
+    }
+}
+
+/// The icons last drawn by [`RingCache::progress_ring`], and what they show.
+type Drawn = ((u32, Tone, Option<String>), Vec<Icon>);
+
+/// Holds on to the last progress ring, as hosts ask for the pixmaps on every
+/// tray update, several times a second, while the ring only moves a step
+/// every so often.
+#[derive(Clone, Default)]
+pub struct RingCache(Arc<Mutex<Option<Drawn>>>);
+
+impl RingCache {
+    /// [`progress_ring`], drawn again only when the step, tone or digits change.
+    pub fn progress_ring(&self, progress: f64, tone: Tone, digits: Option<&str>) -> Vec<Icon> {
+        let key = (step(progress), tone, digits.map(str::to_owned));
+        let mut drawn = self.0.lock().unwrap();
+        match drawn.as_ref() {
+            Some((drawn_key, icons)) if *drawn_key == key => icons.clone(),
+            _ => {
+                let icons = progress_ring(progress, tone, digits);
+                *drawn = Some((key, icons.clone()));
+                icons
+            }
+        }
+    }
+}
+
+/// Which of the [`STEPS`] the ring shows for `progress`.
+fn step(progress: f64) -> u32 {
+    (progress.clamp(0.0, 1.0) * STEPS).ceil() as u32
+}
+
+/// A ring that is full at the start and empties clockwise as `progress`
+/// (the share of time left) runs down, with `digits` in the middle if given.
+fn progress_ring(progress: f64, tone: Tone, digits: Option<&str>) -> Vec<Icon> {
+    let progress = f64::from(step(progress)) / STEPS;
+    SIZES
+        .iter()
+        .filter_map(|&size| {
+            render(size, progress, tone, digits)
+                .map_err(|err| eprintln!("Failed to draw {size}px tray icon: {err}"))
+                .ok()
+        })
+        .collect()
+}
+
+/// Draw the ring, `width` pixels thick, filling a `size` pixel square at the
+/// origin: a faint full track with the share of time left over it.
+pub fn draw_ring(cr: &Context, size: f64, width: f64, progress: f64, tone: Tone) -> Result<()> {
+    let centre = size / 2.0;
+    let radius = (size - width) / 2.0;
+    let (r, g, b) = tone.rgb();
+    cr.set_line_width(width);
+    cr.set_line_cap(LineCap::Butt);
+    cr.set_source_rgba(r, g, b, 0.3);
+    cr.new_sub_path();
+    cr.arc(centre, centre, radius, 0.0, TAU);
+    cr.stroke()?;
+    if progress > 0.0 {
+        cr.set_source_rgb(r, g, b);
+        cr.new_sub_path();
+        cr.arc(centre, centre, radius, -FRAC_PI_2, -FRAC_PI_2 + TAU * progress.min(1.0));
+        cr.stroke()?;
+    }
+    Ok(())
+}
+
+fn render(size: i32, progress: f64, tone: Tone, digits: Option<&str>) -> Result<Icon> {
+    let mut surface = ImageSurface::create(Format::ARgb32, size, size)?;
+    {
+        let cr = Context::new(&surface)?;
+        let s = f64::from(size);
+        let centre = s / 2.0;
+        // A whole number of pixels keeps the ring's edges sharp.
+        let width = (s * 0.16).round().max(2.0);
+        let radius = (s - width) / 2.0;
+
+        if digits.is_some() {
+            // Backing for the digits, legible on light and dark bars alike.
+            cr.set_source_rgba(0.0, 0.0, 0.0, 0.6);
+            cr.arc(centre, centre, radius, 0.0, TAU);
+            cr.fill()?;
+        }
+
+        draw_ring(&cr, s, width, progress, tone)?;
+
+        if let Some(text) = digits {
+            let inner = s - 2.0 * width;
+            let scale = match text.chars().count() {
+                0 | 1 => 0.85,
+                2 => 0.7,
+                _ => 0.5,
+            };
+            cr.select_font_face("Sans", FontSlant::Normal, FontWeight::Bold);
+            cr.set_font_size(inner * scale);
+            let extents = cr.text_extents(text)?;
+            cr.move_to(
+                centre - extents.width() / 2.0 - extents.x_bearing(),
+                centre - extents.height() / 2.0 - extents.y_bearing(),
+            );
+            cr.set_source_rgb(1.0, 1.0, 1.0);
+            cr.show_text(text)?;
+        }
+    }
+    surface.flush();
+
+    // Cairo keeps native-endian, premultiplied pixels; SNI wants straight
+    // ARGB in network byte order.
+    let stride = surface.stride() as usize;
+    let width = size as usize;
+    let pixels = surface.data()?;
+    let mut data = Vec::with_capacity(width * width * 4);
+    for row in pixels.chunks(stride).take(width) {
+        for pixel in row[..width * 4].chunks_exact(4) {
+            let pixel = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            let alpha = pixel >> 24;
+            let straight = |channel: u32| match alpha {
+                0 => 0,
+                _ => ((channel & 0xff) * 255 + alpha / 2) / alpha,
+            } as u8;
+            data.extend([
+                alpha as u8,
+                straight(pixel >> 16),
+                straight(pixel >> 8),
+                straight(pixel),
+            ]);
+        }
+    }
+    Ok(Icon {
+        width: size,
+        height: size,
+        data,
+    })
+}
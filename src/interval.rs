@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+/// Interval training (`tytimer intervals`): `rounds` of work, each followed
+/// by rest except the last, along with how far the workout has got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Intervals {
+    pub rounds: u32,
+    pub work: i64,
+    /// Seconds of rest between rounds; `0` goes straight to the next round.
+    pub rest: i64,
+    pub phase: Phase,
+    /// The current round, counting from 1.
+    pub round: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Work,
+    Rest,
+}
+
+impl Intervals {
+    pub fn new(rounds: u32, work: i64, rest: i64) -> Self {
+        Self {
+            rounds,
+            work,
+            rest,
+            phase: Phase::Work,
+            round: 1,
+        }
+    }
+
+    /// Length of the current phase in seconds.
+    pub fn phase_seconds(&self) -> i64 {
+        match self.phase {
+            Phase::Work => self.work,
+            Phase::Rest => self.rest,
+        }
+    }
+
+    /// Move on to the next phase; `false` once the last round is done.
+    pub fn advance(&mut self) -> bool {
+        match self.phase {
+            Phase::Work if self.round >= self.rounds => return false,
+            Phase::Work if self.rest > 0 => self.phase = Phase::Rest,
+            Phase::Work | Phase::Rest => {
+                self.round += 1;
+                self.phase = Phase::Work;
+            }
+        }
+        true
+    }
+
+    /// Status such as `Round 3/8 · Work`.
+    pub fn describe(&self) -> String {
+        let phase = match self.phase {
+            Phase::Work => "Work",
+            Phase::Rest => "Rest",
+        };
+        format!("Round {}/{} · {phase}", self.round, self.rounds)
+    }
+}
@@ -0,0 +1,236 @@
+use crate::timer::TimerSpec;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long either side waits for the other before giving up.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    List,
+    Control { target: Target, command: Command },
+    /// Start a new timer; only the daemon accepts this.
+    Start { spec: Box<TimerSpec> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    All,
+    /// A timer id or label.
+    Match(String),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Command {
+    Pause,
+    Resume,
+    Stop,
+    Extend { seconds: i64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    Timers { timers: Vec<TimerInfo> },
+    Done {
+        affected: Vec<String>,
+        /// Lap files written by stopwatches that were stopped.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        laps: Vec<PathBuf>,
+    },
+    Started { id: String },
+    Error { message: String },
+}
+
+/// A snapshot of one running timer as reported to clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimerInfo {
+    pub id: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub pid: u32,
+    /// Seconds left; negative once the alarm has gone off.
+    pub remaining: i64,
+    pub original: i64,
+    pub running: bool,
+    pub alarm: bool,
+    /// Counting up: `remaining` is minus the time elapsed.
+    #[serde(default)]
+    pub stopwatch: bool,
+    /// Unix time the timer ends at, if it is running.
+    pub ends_at: Option<u64>,
+}
+
+/// A request waiting to be answered on the GTK main thread.
+pub struct Call {
+    pub request: Request,
+    reply: Sender<Response>,
+}
+
+impl Call {
+    pub fn reply(self, response: Response) {
+        let _ = self.reply.send(response);
+    }
+}
+
+/// Directory holding one control socket per running tytimer process.
+pub fn runtime_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("tytimer-{}", unsafe { libc::getuid() }))
+        });
+    base.join("tytimer")
+}
+
+/// Socket of a standalone timer process.
+pub fn process_socket() -> PathBuf {
+    runtime_dir().join(format!("{}.sock", std::process::id()))
+}
+
+/// Well-known socket of the daemon hosting many timers.
+pub fn daemon_socket() -> PathBuf {
+    runtime_dir().join("daemon.sock")
+}
+
+/// Listening end of a process's control socket; the socket file is removed on drop.
+pub struct IpcServer {
+    path: PathBuf,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl IpcServer {
+    /// Listen on `path`, forwarding requests to the returned receiver.
+    pub fn spawn(path: PathBuf) -> Result<(Self, Receiver<Call>)> {
+        let dir = runtime_dir();
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("creating {}", dir.display()))?;
+        let _ = fs::remove_file(&path);
+        let listener =
+            UnixListener::bind(&path).with_context(|| format!("binding {}", path.display()))?;
+
+        let (call_tx, call_rx) = mpsc::channel();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        {
+            let in_flight = Arc::clone(&in_flight);
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let call_tx = call_tx.clone();
+                    let in_flight = Arc::clone(&in_flight);
+                    in_flight.fetch_add(1, Ordering::SeqCst);
+                    thread::spawn(move || {
+                        if let Err(err) = serve_connection(stream, &call_tx) {
+                            eprintln!("Control connection failed: {err}");
+                        }
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+            });
+        }
+
+        Ok((Self { path, in_flight }, call_rx))
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        // Give a `stop` request that ended the process the chance to be answered.
+        let started = Instant::now();
+        while self.in_flight.load(Ordering::SeqCst) > 0
+            && started.elapsed() < Duration::from_secs(1)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn serve_connection(stream: UnixStream, call_tx: &Sender<Call>) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            call_tx
+                .send(Call {
+                    request,
+                    reply: reply_tx,
+                })
+                .map_err(|_| anyhow!("timer is shutting down"))?;
+            reply_rx
+                .recv_timeout(TIMEOUT)
+                .map_err(|_| anyhow!("timer did not answer in time"))?
+        }
+        Err(err) => Response::Error {
+            message: format!("malformed request: {err}"),
+        },
+    };
+
+    let mut out = serde_json::to_string(&response)?;
+    out.push('\n');
+    (&stream).write_all(out.as_bytes())?;
+    Ok(())
+}
+
+/// Send `request` to every running tytimer process and collect the answers.
+///
+/// Sockets nobody is listening on any more (left behind by a crash) are removed.
+pub fn broadcast(request: &Request) -> Vec<Result<Response>> {
+    let Ok(entries) = fs::read_dir(runtime_dir()) else {
+        return Vec::new();
+    };
+    let mut sockets: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sock"))
+        .collect();
+    sockets.sort();
+
+    sockets
+        .iter()
+        .filter_map(|path| match UnixStream::connect(path) {
+            Ok(stream) => Some(send(stream, request)),
+            Err(_) => {
+                let _ = fs::remove_file(path);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Send `request` to the process listening on `path`.
+pub fn request(path: &Path, request: &Request) -> Result<Response> {
+    let stream =
+        UnixStream::connect(path).with_context(|| format!("connecting to {}", path.display()))?;
+    send(stream, request)
+}
+
+fn send(stream: UnixStream, request: &Request) -> Result<Response> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    (&stream).write_all(line.as_bytes())?;
+
+    let mut answer = String::new();
+    BufReader::new(&stream).read_line(&mut answer)?;
+    Ok(serde_json::from_str(&answer)?)
+}
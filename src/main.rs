@@ -1,42 +1,350 @@
+mod aggregate;
 mod app;
 mod audio;
+mod config;
+mod control;
+mod daemon;
+mod dbus;
+mod duration;
+mod host;
+mod icon;
+mod interval;
+mod ipc;
+mod notify;
+mod persist;
+mod pomodoro;
+mod schedule;
+mod stopwatch;
+mod timer;
 mod tray;
 mod ui;
+mod wallclock;
+mod warning;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::cell::RefCell;
+use std::path::PathBuf;
 use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use timer::TimerSpec;
 use gtk4::prelude::*;
 use gtk4::gio::ApplicationFlags;
 
 #[derive(Parser, Debug)]
-#[command(name = "tytimer", about = "Hyprland-friendly timer rewritten in Rust", version)]
+#[command(
+    name = "tytimer",
+    about = "Hyprland-friendly timer rewritten in Rust",
+    version,
+    args_conflicts_with_subcommands = true
+)]
 struct Args {
-    /// Timer length in minutes (decimals allowed). If omitted, opens GUI to set duration.
-    minutes: Option<f64>,
+    #[command(subcommand)]
+    command: Option<Cmd>,
+    /// Timer length, e.g. `25`, `2.5m`, `90s`, `1h30m` or `1:30:00` (bare numbers are
+    /// minutes), or the name of a preset from the config. If omitted, opens GUI to set
+    /// duration.
+    #[arg(value_name = "DURATION|PRESET")]
+    duration: Option<String>,
+    /// End at a local clock time instead, e.g. `14:30`, `2:30pm` or `tomorrow 09:00`
+    #[arg(long, value_name = "TIME", conflicts_with = "duration", value_parser = wallclock::parse)]
+    at: Option<SystemTime>,
+    /// Name for the timer, shown in the tray and alarm window
+    #[arg(long, short)]
+    label: Option<String>,
+    /// Text to show in the alarm window when the timer goes off
+    #[arg(long, short)]
+    message: Option<String>,
+    #[command(flatten)]
+    alarm: AlarmArgs,
     /// Run in foreground without daemonizing
     #[arg(long)]
     no_daemon: bool,
+    /// Timer description as JSON, used when handing a timer to a child process
+    #[arg(long, hide = true, conflicts_with_all = ["duration", "at", "label", "message"])]
+    spec: Option<String>,
+}
+
+/// How the alarm rings.
+#[derive(clap::Args, Debug)]
+struct AlarmArgs {
+    /// Audio file to play instead of the built-in alarm sound
+    #[arg(long, value_name = "PATH")]
+    sound: Option<PathBuf>,
+    /// Play the alarm sound once instead of repeating it until stopped or snoozed
+    #[arg(long)]
+    no_repeat: bool,
+    /// Fade the alarm in from quiet to full volume over this long
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    ramp: Option<i64>,
+    /// Stop repeating the sound after this long, even if not acknowledged
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    ring_for: Option<i64>,
+    /// Ring again this often until the alarm is stopped or snoozed
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    realert: Option<i64>,
+    /// Also send a desktop notification with Stop and Snooze buttons
+    #[arg(long, overrides_with = "no_notify")]
+    notify: bool,
+    /// Don't send a desktop notification, even if the config asks for one
+    #[arg(long, overrides_with = "notify")]
+    no_notify: bool,
+    /// Chime softly this long (or this share of the timer) before the end, e.g. 5m,50%
+    #[arg(long, value_name = "WHEN", value_delimiter = ',', value_parser = warning::Warning::parse)]
+    warn: Vec<warning::Warning>,
+}
+
+impl AlarmArgs {
+    /// The configured alarm defaults, overridden by whatever was given here.
+    fn options(&self, defaults: &config::AlarmConfig) -> anyhow::Result<audio::AlarmOptions> {
+        let defaults = defaults.options();
+        // The timer may run in another process with another working directory.
+        let sound = self.sound.as_deref().map(std::path::absolute).transpose()?;
+        Ok(audio::AlarmOptions {
+            sound: sound.or(defaults.sound),
+            repeat: defaults.repeat && !self.no_repeat,
+            ramp: self.ramp.or(defaults.ramp),
+            ring_for: self.ring_for.or(defaults.ring_for),
+            realert: self.realert.or(defaults.realert),
+            notify: (defaults.notify || self.notify) && !self.no_notify,
+            warn: if self.warn.is_empty() {
+                defaults.warn
+            } else {
+                self.warn.clone()
+            },
+        })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// List running timers
+    List {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Pause running timers
+    Pause(Selection),
+    /// Resume paused timers
+    Resume(Selection),
+    /// Stop timers and close them
+    Stop(Selection),
+    /// Alternate work and break phases in one timer
+    Pomodoro(PomodoroArgs),
+    /// Interval training: rounds of work and rest with countdown beeps
+    Intervals {
+        /// Number of work rounds
+        #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+        rounds: u32,
+        /// Length of each work round
+        #[arg(long, value_name = "DURATION", default_value = "20s", value_parser = duration::parse)]
+        work: i64,
+        /// Rest between rounds; `0` for none
+        #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = duration::parse_or_zero)]
+        rest: i64,
+        /// Name for the timer, shown in the tray and alarm window
+        #[arg(long, short, default_value = "Intervals")]
+        label: String,
+    },
+    /// Count up from zero, recording laps from the tray or window
+    Stopwatch {
+        /// Name for the stopwatch, shown in the tray and window
+        #[arg(long, short, default_value = "Stopwatch")]
+        label: String,
+    },
+    /// Host all new timers in one long-running process
+    Daemon,
+    /// Inspect the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCmd,
+    },
+    /// Bring back timers left over from a previous session (e.g. from autostart)
+    Restore,
+    /// Recurring reminders, started by the daemon
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleCmd,
+    },
+    /// Add time to timers
+    Extend {
+        #[command(flatten)]
+        selection: Selection,
+        /// How much time to add
+        #[arg(long, value_name = "DURATION", default_value = "1m", value_parser = duration::parse)]
+        by: i64,
+    },
+}
+
+/// Overrides for the `[pomodoro]` config section.
+#[derive(clap::Args, Debug)]
+struct PomodoroArgs {
+    /// Length of a work phase
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    work: Option<i64>,
+    /// Length of a short break
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    short_break: Option<i64>,
+    /// Length of a long break
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    long_break: Option<i64>,
+    /// Work phases between long breaks
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    long_every: Option<u32>,
+    /// Sound announcing a work phase
+    #[arg(long, value_name = "PATH")]
+    work_sound: Option<PathBuf>,
+    /// Sound announcing a short break
+    #[arg(long, value_name = "PATH")]
+    break_sound: Option<PathBuf>,
+    /// Sound announcing a long break
+    #[arg(long, value_name = "PATH")]
+    long_break_sound: Option<PathBuf>,
+    /// Name for the timer, shown in the tray and alarm window
+    #[arg(long, short, default_value = "Pomodoro")]
+    label: String,
+}
+
+impl PomodoroArgs {
+    fn spec(&self, config: &config::Config) -> anyhow::Result<TimerSpec> {
+        let defaults = &config.pomodoro;
+        // Command-line paths are made absolute for the process that runs the timer.
+        let sound = |arg: &Option<PathBuf>, default: &Option<PathBuf>| match arg {
+            Some(path) => std::path::absolute(path).map(Some),
+            None => Ok(default.as_deref().map(config::expand_home)),
+        };
+        Ok(TimerSpec {
+            label: Some(self.label.clone()),
+            alarm: config.alarm.options(),
+            pomodoro: Some(pomodoro::Pomodoro {
+                work: self.work.unwrap_or(defaults.work),
+                short_break: self.short_break.unwrap_or(defaults.short_break),
+                long_break: self.long_break.unwrap_or(defaults.long_break),
+                long_every: self.long_every.unwrap_or(defaults.long_every).max(1),
+                work_sound: sound(&self.work_sound, &defaults.work_sound)?,
+                break_sound: sound(&self.break_sound, &defaults.break_sound)?,
+                long_break_sound: sound(&self.long_break_sound, &defaults.long_break_sound)?,
+                phase: pomodoro::Phase::Work,
+                cycle: 1,
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCmd {
+    /// Report problems in the config file
+    Check,
+    /// Print where the config file is read from
+    Path,
+}
+
+#[derive(Subcommand, Debug)]
+enum ScheduleCmd {
+    /// Add a reminder, e.g. "weekdays 09:55", "every 45m between 09:00 and 18:00"
+    /// or a cron expression like "*/30 9-17 * * 1-5"
+    Add {
+        rule: String,
+        /// Name for the timers, shown in the tray and alarm window
+        #[arg(long, short)]
+        label: Option<String>,
+        /// Text shown in the alarm window
+        #[arg(long, short)]
+        message: Option<String>,
+        /// Start counting down this long before each occurrence
+        #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+        lead: Option<i64>,
+    },
+    /// List schedules and when they next fire
+    List {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete a schedule by id
+    Remove { id: u32 },
+}
+
+#[derive(clap::Args, Debug)]
+struct Selection {
+    /// Timer id or label (see `tytimer list`)
+    #[arg(required_unless_present = "all")]
+    timer: Option<String>,
+    /// Apply to every running timer
+    #[arg(long, conflicts_with = "timer")]
+    all: bool,
+    /// Print JSON instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+impl Selection {
+    fn target(&self) -> ipc::Target {
+        match &self.timer {
+            Some(timer) if !self.all => ipc::Target::Match(timer.clone()),
+            _ => ipc::Target::All,
+        }
+    }
+}
+
+/// Words `tytimer <word>` takes as a subcommand rather than a preset.
+fn subcommand_names() -> Vec<String> {
+    let command = Args::command();
+    let mut names: Vec<String> = command
+        .get_subcommands()
+        .flat_map(|sub| std::iter::once(sub.get_name()).chain(sub.get_all_aliases()))
+        .map(String::from)
+        .collect();
+    // Added by clap when it builds the parser.
+    names.push("help".into());
+    names
+}
+
+/// The config for starting timers from the command line, less the presets
+/// hidden behind subcommands.
+fn load_config() -> config::Config {
+    let mut config = config::load_or_default();
+    for warning in config.drop_shadowed_presets(&subcommand_names()) {
+        eprintln!("{}: {warning}", config::path().display());
+    }
+    config
 }
 
 fn main() {
     let args = Args::parse();
 
-    // Handle case where no minutes argument is provided
-    if args.minutes.is_none() {
-        run_setter_gui();
+    if let Some(command) = &args.command {
+        if let Err(err) = run_command(command) {
+            eprintln!("{err}");
+            process::exit(1);
+        }
         return;
     }
 
-    let minutes = args.minutes.unwrap();
-    if minutes <= 0.0 {
-        eprintln!("Minutes must be positive.");
-        process::exit(2);
+    // Timers from a session that ended early (crash, logout, reboot) come back
+    // with the next launch; a `--spec` child is itself such a launch.
+    if args.spec.is_none() {
+        restore_saved();
     }
 
+    let spec = match spec_from_args(&args) {
+        Ok(Some(spec)) => spec,
+        // Handle case where no duration argument is provided
+        Ok(None) => {
+            run_setter_gui();
+            return;
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
+        }
+    };
+
     if !args.no_daemon {
-        match daemonize(minutes) {
-            Ok(()) => {
-                println!("✅ Timer started in background.");
+        match start_detached(&spec) {
+            Ok(started) => {
+                println!("✅ {started}");
                 return;
             }
             Err(err) => {
@@ -50,8 +358,172 @@ fn main() {
         eprintln!("Failed to init GStreamer (sound will be disabled): {err}");
     }
 
-    let total_seconds = (minutes * 60.0).round().max(1.0) as i64;
-    run_timer(total_seconds);
+    run_timer(spec);
+}
+
+fn spec_from_args(args: &Args) -> anyhow::Result<Option<TimerSpec>> {
+    if let Some(json) = &args.spec {
+        return Ok(Some(serde_json::from_str(json)?));
+    }
+    let config = load_config();
+    let mut preset = None;
+    let mut spec = match (&args.duration, args.at) {
+        (Some(text), _) => {
+            let seconds = match duration::parse(text) {
+                Ok(seconds) => seconds,
+                Err(err) => {
+                    let found = find_preset(&config, text, err)?;
+                    preset = Some(found);
+                    found.1.duration
+                }
+            };
+            TimerSpec {
+                seconds: Some(seconds),
+                ..Default::default()
+            }
+        }
+        (None, Some(target)) => TimerSpec {
+            at: Some(target.duration_since(UNIX_EPOCH)?.as_secs()),
+            ..Default::default()
+        },
+        (None, None) => return Ok(None),
+    };
+    // Options given on the command line win over the preset's.
+    spec.label = args.label.clone().or_else(|| {
+        let (name, preset) = preset?;
+        Some(preset.label.clone().unwrap_or_else(|| name.to_string()))
+    });
+    spec.message = args
+        .message
+        .clone()
+        .or_else(|| preset.and_then(|(_, preset)| preset.message.clone()));
+    spec.alarm = args.alarm.options(&config.alarm)?;
+    if let (None, Some(sound)) = (&args.alarm.sound, preset.and_then(|(_, p)| p.sound.as_deref())) {
+        spec.alarm.sound = Some(config::expand_home(sound));
+    }
+    Ok(Some(spec))
+}
+
+/// Look up the preset called `name`; `not_duration` explains why `name` was
+/// not read as a duration instead.
+fn find_preset<'a>(
+    config: &'a config::Config,
+    name: &str,
+    not_duration: anyhow::Error,
+) -> anyhow::Result<(&'a str, &'a config::Preset)> {
+    if let Some((name, preset)) = config.presets.get_key_value(name) {
+        return Ok((name.as_str(), preset));
+    }
+    if config.presets.is_empty() {
+        return Err(not_duration);
+    }
+    let names: Vec<&str> = config.presets.keys().map(String::as_str).collect();
+    anyhow::bail!(
+        "{not_duration}, nor a preset (presets: {})",
+        names.join(", ")
+    )
+}
+
+fn run_command(command: &Cmd) -> anyhow::Result<()> {
+    use ipc::Command;
+
+    match command {
+        Cmd::List { json } => control::list(*json),
+        Cmd::Pause(sel) => control::control(sel.target(), Command::Pause, sel.json),
+        Cmd::Resume(sel) => control::control(sel.target(), Command::Resume, sel.json),
+        Cmd::Stop(sel) => control::control(sel.target(), Command::Stop, sel.json),
+        Cmd::Pomodoro(args) => {
+            let spec = args.spec(&config::load_or_default())?;
+            println!("✅ {}", start_detached(&spec)?);
+            Ok(())
+        }
+        Cmd::Intervals {
+            rounds,
+            work,
+            rest,
+            label,
+        } => {
+            let spec = TimerSpec {
+                label: Some(label.clone()),
+                alarm: config::load_or_default().alarm.options(),
+                intervals: Some(interval::Intervals::new(*rounds, *work, *rest)),
+                ..Default::default()
+            };
+            println!("✅ {}", start_detached(&spec)?);
+            Ok(())
+        }
+        Cmd::Stopwatch { label } => {
+            let spec = TimerSpec {
+                label: Some(label.clone()),
+                stopwatch: Some(stopwatch::Stopwatch::default()),
+                ..Default::default()
+            };
+            println!("✅ {}", start_detached(&spec)?);
+            Ok(())
+        }
+        Cmd::Daemon => {
+            if let Err(err) = gstreamer::init() {
+                eprintln!("Failed to init GStreamer (sound will be disabled): {err}");
+            }
+            daemon::run()
+        }
+        Cmd::Extend { selection, by } => control::control(
+            selection.target(),
+            Command::Extend { seconds: *by },
+            selection.json,
+        ),
+        Cmd::Config { action } => match action {
+            ConfigCmd::Check => config::check(&subcommand_names()),
+            ConfigCmd::Path => {
+                println!("{}", config::path().display());
+                Ok(())
+            }
+        },
+        Cmd::Restore => {
+            if restore_saved() == 0 {
+                println!("No saved timers to restore.");
+            }
+            Ok(())
+        }
+        Cmd::Schedule { action } => match action {
+            ScheduleCmd::Add {
+                rule,
+                label,
+                message,
+                lead,
+            } => schedule::add(rule, label.clone(), message.clone(), *lead),
+            ScheduleCmd::List { json } => schedule::list(*json),
+            ScheduleCmd::Remove { id } => schedule::remove(*id),
+        },
+    }
+}
+
+/// Restart every orphaned saved timer, returning how many were found.
+fn restore_saved() -> usize {
+    let orphans = persist::claim_orphans();
+    for spec in &orphans {
+        if let Some(missed) = spec.state().ok().and_then(|state| state.missed) {
+            println!(
+                "⏰ A timer ended at {} while tytimer was not running.",
+                wallclock::format_local(missed)
+            );
+        }
+        match start_detached(spec) {
+            Ok(started) => println!("↩️ Restored: {started}"),
+            Err(err) => eprintln!("Failed to restore timer: {err}"),
+        }
+    }
+    orphans.len()
+}
+
+/// Run `spec` outside this process: in the daemon if one is up, otherwise
+/// in a background child of its own.
+fn start_detached(spec: &TimerSpec) -> anyhow::Result<String> {
+    if let Ok(id) = daemon::hand_off(spec) {
+        return Ok(format!("Timer {id} started in the tytimer daemon."));
+    }
+    daemonize(spec)?;
+    Ok("Timer started in background.".to_string())
 }
 
 fn run_setter_gui() {
@@ -64,36 +536,58 @@ fn run_setter_gui() {
         .flags(ApplicationFlags::NON_UNIQUE)
         .build();
 
+    let config = config::load_or_default();
     app.connect_activate(move |gtk_app| {
-        let setter = ui::SetterWindow::new(gtk_app);
+        let setter = ui::SetterWindow::new(gtk_app, &config);
         setter.present();
     });
 
     app.run_with_args(&Vec::<&str>::new());
 }
 
-fn run_timer(total_seconds: i64) {
+fn run_timer(spec: TimerSpec) {
     let app = gtk4::Application::builder()
         .application_id("dev.ty.timers")
         .flags(ApplicationFlags::NON_UNIQUE)
         .build();
 
+    // Control socket for `tytimer list/pause/...`; the timer still runs without it.
+    let (ipc_server, ipc_calls) = match ipc::IpcServer::spawn(ipc::process_socket()) {
+        Ok((server, calls)) => (Some(server), Some(calls)),
+        Err(err) => {
+            eprintln!("Control socket failed to start: {err}");
+            (None, None)
+        }
+    };
+
+    let ipc_calls = RefCell::new(ipc_calls);
     app.connect_activate(move |gtk_app| {
-        let app_state = app::TimerApp::new(gtk_app, total_seconds);
-        app_state.present();
+        let host = host::Host::new(gtk_app, false);
+        if let Err(err) = host.start(&spec) {
+            eprintln!("Failed to start timer: {err}");
+            gtk_app.quit();
+            return;
+        }
+        if let Some(calls) = ipc_calls.borrow_mut().take() {
+            host.serve(calls);
+        }
     });
 
     // Pass no args to GTK so our custom flags don't trigger "Unknown option"
     app.run_with_args(&Vec::<&str>::new());
+    drop(ipc_server);
 }
 
-fn daemonize(minutes: f64) -> anyhow::Result<()> {
+fn daemonize(spec: &TimerSpec) -> anyhow::Result<()> {
     use std::process::{Command, Stdio};
 
     let exe = std::env::current_exe()?;
     let mut cmd = Command::new(exe);
+    // Hand over the resolved spec so the child cannot re-resolve "14:30"
+    // differently a few milliseconds later.
     cmd.arg("--no-daemon")
-        .arg(minutes.to_string())
+        .arg("--spec")
+        .arg(serde_json::to_string(spec)?)
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null());
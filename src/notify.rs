@@ -0,0 +1,339 @@
+use crate::duration;
+use crate::tray::TrayAction;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::Type;
+use zbus::zvariant::Value;
+use zbus::MatchRule;
+
+const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// What the notification's "Snooze" button snoozes for.
+const SNOOZE_SECONDS: i64 = 5 * 60;
+
+/// Desktop notifications (`org.freedesktop.Notifications`) for finished
+/// timers, with Stop and Snooze buttons.
+///
+/// Calls to the server go out from a thread of their own, so a slow or
+/// missing server never holds up the timers; a notification that could not
+/// be shown is reported to its timer as `TrayAction::NotificationFailed`.
+/// Button presses arrive as `ActionInvoked` signals, which another thread
+/// turns into `TrayAction`s for the timer that showed the notification. The
+/// signals are matched on interface and path only, not on the sender, so any
+/// server that speaks the protocol works, mock ones included.
+#[derive(Clone)]
+pub struct Notifier {
+    jobs: Sender<Job>,
+    /// The ticket for the next notification shown.
+    next_ticket: Arc<AtomicU32>,
+}
+
+/// Work for the thread that talks to the server.
+enum Job {
+    Show {
+        ticket: u32,
+        summary: String,
+        body: String,
+        action_tx: Sender<TrayAction>,
+    },
+    Close(u32),
+}
+
+/// Where each open notification's button presses go, by notification id.
+type Routes = Arc<Mutex<HashMap<u32, Sender<TrayAction>>>>;
+
+impl Notifier {
+    pub fn connect() -> Result<Self> {
+        Self::with_connection(Connection::session()?)
+    }
+
+    /// Talk to the notification server on `conn`'s bus.
+    fn with_connection(conn: Connection) -> Result<Self> {
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(NOTIFICATIONS)?
+            .path(NOTIFICATIONS_PATH)?
+            .build();
+        let signals = MessageIterator::for_match_rule(rule, &conn, None)?;
+        let routes = Routes::default();
+        {
+            let routes = Arc::clone(&routes);
+            std::thread::Builder::new()
+                .name("tytimer-notify".into())
+                .spawn(move || listen(signals, routes))?;
+        }
+        let (jobs, queue) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("tytimer-notify-send".into())
+            .spawn(move || work(conn, queue, routes))?;
+        Ok(Self {
+            jobs,
+            next_ticket: Arc::new(AtomicU32::new(1)),
+        })
+    }
+
+    /// Show a notification whose buttons are delivered on `action_tx`,
+    /// returning a ticket to [`close`](Self::close) it with.
+    pub fn show(&self, summary: &str, body: &str, action_tx: Sender<TrayAction>) -> u32 {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        let _ = self.jobs.send(Job::Show {
+            ticket,
+            summary: summary.to_string(),
+            body: body.to_string(),
+            action_tx,
+        });
+        ticket
+    }
+
+    /// Take down the notification shown with `ticket`, e.g. once the alarm
+    /// was answered elsewhere.
+    pub fn close(&self, ticket: u32) {
+        let _ = self.jobs.send(Job::Close(ticket));
+    }
+}
+
+/// Carry out `queue` in order on `conn`.
+fn work(conn: Connection, queue: Receiver<Job>, routes: Routes) {
+    // The server's id for each ticket.
+    let mut ids = HashMap::new();
+    for job in queue {
+        match job {
+            Job::Show {
+                ticket,
+                summary,
+                body,
+                action_tx,
+            } => match show(&conn, &summary, &body) {
+                Ok(id) => {
+                    routes.lock().unwrap().insert(id, action_tx);
+                    ids.insert(ticket, id);
+                }
+                Err(err) => {
+                    eprintln!("Failed to send notification: {err}");
+                    let _ = action_tx.send(TrayAction::NotificationFailed);
+                }
+            },
+            Job::Close(ticket) => {
+                let Some(id) = ids.remove(&ticket) else {
+                    continue;
+                };
+                // Already gone if the server said it closed.
+                if routes.lock().unwrap().remove(&id).is_none() {
+                    continue;
+                }
+                if let Err(err) = conn.call_method(
+                    Some(NOTIFICATIONS),
+                    NOTIFICATIONS_PATH,
+                    Some(NOTIFICATIONS),
+                    "CloseNotification",
+                    &(id,),
+                ) {
+                    eprintln!("Failed to close notification {id}: {err}");
+                }
+            }
+        }
+    }
+}
+
+/// Ask the server to show a notification, returning its id.
+fn show(conn: &Connection, summary: &str, body: &str) -> Result<u32> {
+    let snooze = format!("Snooze {}", duration::format(SNOOZE_SECONDS));
+    let actions = vec!["default", "Show", "stop", "Stop", "snooze", snooze.as_str()];
+    let mut hints: HashMap<&str, Value> = HashMap::new();
+    // Critical: stays up until answered.
+    hints.insert("urgency", Value::U8(2));
+    let reply = conn.call_method(
+        Some(NOTIFICATIONS),
+        NOTIFICATIONS_PATH,
+        Some(NOTIFICATIONS),
+        "Notify",
+        &("tytimer", 0u32, "alarm-symbolic", summary, body, actions, hints, 0i32),
+    )?;
+    Ok(reply.body().deserialize()?)
+}
+
+/// Forward button presses to the timers that own the notifications, and
+/// forget notifications once the server closes them.
+fn listen(signals: MessageIterator, routes: Routes) {
+    for message in signals.flatten() {
+        let header = message.header();
+        let Some(member) = header.member() else {
+            continue;
+        };
+        match member.as_str() {
+            "ActionInvoked" => {
+                let Ok((id, key)) = message.body().deserialize::<(u32, String)>() else {
+                    continue;
+                };
+                let action = match key.as_str() {
+                    "stop" => TrayAction::Quit,
+                    "snooze" => TrayAction::Snooze(SNOOZE_SECONDS),
+                    "default" => TrayAction::ShowAlarm,
+                    _ => continue,
+                };
+                if let Some(action_tx) = routes.lock().unwrap().get(&id) {
+                    let _ = action_tx.send(action);
+                }
+            }
+            "NotificationClosed" => {
+                if let Ok((id, _reason)) = message.body().deserialize::<(u32, u32)>() {
+                    routes.lock().unwrap().remove(&id);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::Duration;
+    use zbus::blocking::connection::Builder;
+    use zbus::zvariant::OwnedValue;
+
+    /// A notification server that passes on the ids it shows and closes.
+    struct Server {
+        next_id: u32,
+        shown: mpsc::Sender<u32>,
+        closed: mpsc::Sender<u32>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl Server {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &mut self,
+            _app_name: String,
+            _replaces_id: u32,
+            _icon: String,
+            _summary: String,
+            _body: String,
+            _actions: Vec<String>,
+            _hints: HashMap<String, OwnedValue>,
+            _timeout: i32,
+        ) -> u32 {
+            self.next_id += 1;
+            let _ = self.shown.send(self.next_id);
+            self.next_id
+        }
+
+        fn close_notification(&self, id: u32) {
+            let _ = self.closed.send(id);
+        }
+    }
+
+    /// A bus of our own, so the test neither needs nor disturbs a session.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// `None` if `dbus-daemon` isn't installed.
+        fn start() -> Option<Self> {
+            let mut daemon = match Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+            {
+                Ok(daemon) => daemon,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+                Err(err) => panic!("starting dbus-daemon: {err}"),
+            };
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> Builder<'static> {
+            Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn invoke(server: &Connection, id: u32, key: &str) {
+        server
+            .emit_signal(
+                None::<&str>,
+                NOTIFICATIONS_PATH,
+                NOTIFICATIONS,
+                "ActionInvoked",
+                &(id, key),
+            )
+            .unwrap();
+    }
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn buttons_reach_the_timer_and_acknowledging_closes() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let (shown_tx, shown) = mpsc::channel();
+        let (closed_tx, closed) = mpsc::channel();
+        let server = bus
+            .connect()
+            .name(NOTIFICATIONS)
+            .unwrap()
+            .serve_at(
+                NOTIFICATIONS_PATH,
+                Server {
+                    next_id: 0,
+                    shown: shown_tx,
+                    closed: closed_tx,
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let notifier = Notifier::with_connection(bus.connect().build().unwrap()).unwrap();
+
+        let (action_tx, action_rx) = mpsc::channel();
+        let ticket = notifier.show("Tea: time's up", "", action_tx);
+        let id = shown.recv_timeout(WAIT).unwrap();
+        let next = || action_rx.recv_timeout(WAIT).unwrap();
+        invoke(&server, id, "snooze");
+        assert!(matches!(next(), TrayAction::Snooze(300)));
+        invoke(&server, id, "stop");
+        assert!(matches!(next(), TrayAction::Quit));
+
+        notifier.close(ticket);
+        assert_eq!(closed.recv_timeout(WAIT).unwrap(), id);
+    }
+
+    #[test]
+    fn a_missing_server_is_reported_to_the_timer() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let notifier = Notifier::with_connection(bus.connect().build().unwrap()).unwrap();
+        let (action_tx, action_rx) = mpsc::channel();
+        notifier.show("Tea: time's up", "", action_tx);
+        assert!(matches!(
+            action_rx.recv_timeout(WAIT).unwrap(),
+            TrayAction::NotificationFailed
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::timer::TimerSpec;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One timer's state file, rewritten whenever the timer changes.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedTimer {
+    /// Identifies the boot the owning process ran in; pids are only
+    /// meaningful within one boot.
+    boot_id: String,
+    pid: u32,
+    spec: TimerSpec,
+}
+
+/// `$XDG_STATE_HOME/tytimer`, falling back to `~/.local/state/tytimer`.
+pub fn state_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("tytimer")
+}
+
+fn timers_dir() -> PathBuf {
+    state_dir().join("timers")
+}
+
+/// Record `spec` (which should carry a fresh snapshot) as the state of timer `id`.
+pub fn save(id: &str, spec: &TimerSpec) -> Result<()> {
+    let dir = timers_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let saved = SavedTimer {
+        boot_id: boot_id(),
+        pid: std::process::id(),
+        spec: spec.clone(),
+    };
+    // Write then rename so a crash mid-write never leaves a torn file behind.
+    let path = dir.join(format!("{id}.json"));
+    let tmp = dir.join(format!(".{id}.json.tmp"));
+    fs::write(&tmp, serde_json::to_vec_pretty(&saved)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Forget timer `id`, once it has been stopped.
+pub fn remove(id: &str) {
+    let _ = fs::remove_file(timers_dir().join(format!("{id}.json")));
+}
+
+/// A line of `history.jsonl`, written when a timer is stopped.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub label: Option<String>,
+    /// Length the timer was set for, in seconds.
+    pub original: i64,
+    /// Unix time the timer was stopped.
+    pub stopped_at: u64,
+    /// Whether the alarm went off before it was stopped.
+    pub alarm: bool,
+    /// Seconds the alarm was left ringing past the end.
+    pub overtime: i64,
+    pub snoozes: u32,
+}
+
+/// Append `entry` to `$XDG_STATE_HOME/tytimer/history.jsonl`.
+pub fn record_history(entry: &HistoryEntry) -> Result<()> {
+    let dir = state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let path = dir.join("history.jsonl");
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    // One write per line, so timers stopping together never interleave.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(&line))
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+/// Take over the saved timers whose process is gone (crash, logout, reboot).
+///
+/// Each file is claimed by renaming it first, so two launches racing each
+/// other never restore the same timer twice.
+pub fn claim_orphans() -> Vec<TimerSpec> {
+    let Ok(entries) = fs::read_dir(timers_dir()) else {
+        return Vec::new();
+    };
+    let boot = boot_id();
+    let mut orphans = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let saved = match read(&path) {
+            Ok(saved) => saved,
+            Err(err) => {
+                eprintln!("Ignoring unreadable timer state {}: {err}", path.display());
+                continue;
+            }
+        };
+        if saved.boot_id == boot && owner_running(saved.pid) {
+            continue;
+        }
+        let claimed = path.with_extension(format!("claimed-{}", std::process::id()));
+        if fs::rename(&path, &claimed).is_ok() {
+            let _ = fs::remove_file(&claimed);
+            orphans.push(saved.spec);
+        }
+    }
+    orphans
+}
+
+fn read(path: &Path) -> Result<SavedTimer> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+fn boot_id() -> String {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether `pid` is still a tytimer process (and not an unrelated one that
+/// reused the number).
+fn owner_running(pid: u32) -> bool {
+    if !process_alive(pid) {
+        return false;
+    }
+    let ours = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_name().map(|name| name.to_string_lossy().into_owned()));
+    match (fs::read_to_string(format!("/proc/{pid}/comm")), ours) {
+        // The kernel truncates `comm` to 15 bytes.
+        (Ok(comm), Some(ours)) => ours.as_bytes().starts_with(comm.trim_end().as_bytes()),
+        _ => true,
+    }
+}
+
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks for existence; EPERM still means it exists.
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
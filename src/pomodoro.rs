@@ -0,0 +1,81 @@
+use crate::duration;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A work/break session run by a single timer (`tytimer pomodoro`), along
+/// with how far it has got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pomodoro {
+    pub work: i64,
+    pub short_break: i64,
+    pub long_break: i64,
+    /// A long break replaces the short one after every this many work phases.
+    pub long_every: u32,
+    /// Sounds announcing each phase; `None` uses the timer's alarm sound.
+    pub work_sound: Option<PathBuf>,
+    pub break_sound: Option<PathBuf>,
+    pub long_break_sound: Option<PathBuf>,
+    pub phase: Phase,
+    /// The work phase we are in or just finished, counting from 1.
+    pub cycle: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Pomodoro {
+    /// Length of the current phase in seconds.
+    pub fn phase_seconds(&self) -> i64 {
+        match self.phase {
+            Phase::Work => self.work,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break,
+        }
+    }
+
+    /// Move on to the phase after the current one.
+    pub fn advance(&mut self) {
+        self.phase = match self.phase {
+            Phase::Work if self.cycle.is_multiple_of(self.long_every.max(1)) => Phase::LongBreak,
+            Phase::Work => Phase::ShortBreak,
+            Phase::ShortBreak | Phase::LongBreak => {
+                self.cycle += 1;
+                Phase::Work
+            }
+        };
+    }
+
+    /// The sound that announces the current phase.
+    pub fn sound(&self) -> Option<&Path> {
+        match self.phase {
+            Phase::Work => self.work_sound.as_deref(),
+            Phase::ShortBreak => self.break_sound.as_deref(),
+            Phase::LongBreak => self.long_break_sound.as_deref(),
+        }
+    }
+
+    /// Short status such as `Work 2/4` or `Long break`.
+    pub fn describe(&self) -> String {
+        let position = self.cycle.saturating_sub(1) % self.long_every.max(1) + 1;
+        match self.phase {
+            Phase::Work => format!("Work {position}/{}", self.long_every),
+            Phase::ShortBreak => format!("Break {position}/{}", self.long_every),
+            Phase::LongBreak => "Long break".into(),
+        }
+    }
+
+    /// What to tell the user as the current phase begins.
+    pub fn announcement(&self) -> String {
+        let length = duration::format(self.phase_seconds());
+        match self.phase {
+            Phase::Work => format!("Break over: back to work (cycle {})", self.cycle),
+            Phase::ShortBreak => format!("Work done: take a {length} break"),
+            Phase::LongBreak => format!("{} cycles done: take a {length} break", self.cycle),
+        }
+    }
+}
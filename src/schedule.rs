@@ -0,0 +1,703 @@
+use crate::config;
+use crate::duration;
+use crate::host::Host;
+use crate::ipc::{self, Request};
+use crate::persist;
+use crate::timer::TimerSpec;
+use crate::wallclock;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::fs::{self, File};
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+/// An occurrence this late (the daemon was not running) is skipped, not rung.
+const GRACE_SECONDS: i64 = 60;
+
+/// How far ahead to look for the next occurrence; enough for `29 2` in cron.
+const SEARCH_DAYS: u64 = 5 * 366;
+
+/// A recurring reminder, kept in `$XDG_STATE_HOME/tytimer/schedules.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: u32,
+    /// The rule as it was written, e.g. `weekdays 09:55`; see [`Rule::parse`].
+    pub rule: String,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    /// Start the timer this many seconds early, so it counts down to the
+    /// occurrence instead of ringing straight away.
+    #[serde(default)]
+    pub lead: i64,
+    /// Unix time; occurrences after it have yet to fire.
+    pub armed_from: i64,
+    /// The first occurrence after `armed_from`, worked out by [`Schedule::arm`]
+    /// rather than on every check; `None` if the rule never fires.
+    #[serde(skip)]
+    next: Option<DateTime<Local>>,
+}
+
+impl Schedule {
+    /// Work out when the schedule next fires, after a load or a change to
+    /// `armed_from`.
+    fn arm(&mut self) {
+        self.next = Rule::parse(&self.rule).ok().and_then(|rule| {
+            let armed_from = DateTime::from_timestamp(self.armed_from, 0)?;
+            rule.next_after(armed_from.with_timezone(&Local))
+        });
+    }
+}
+
+/// When a schedule fires.
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// `weekdays 09:55`, `every mon,wed at 18:00`, `daily 7:30am`
+    At { days: Weekdays, time: NaiveTime },
+    /// `every 45m between 09:00 and 18:00 on weekdays`
+    Every {
+        interval: i64,
+        from: NaiveTime,
+        to: NaiveTime,
+        days: Weekdays,
+    },
+    /// Five cron fields: `*/15 9-17 * * 1-5`
+    Cron(Cron),
+}
+
+/// A set of days of the week, Monday first.
+#[derive(Debug, Clone, Copy)]
+pub struct Weekdays([bool; 7]);
+
+/// A cron expression, each field as a bit set of the values it allows.
+#[derive(Debug, Clone)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    /// Sunday is bit 0, as in cron.
+    weekdays: u64,
+    /// Cron matches either day field when both are restricted.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Rule {
+    pub fn parse(input: &str) -> Result<Self> {
+        let text = input.trim().to_ascii_lowercase();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.len() == 5
+            && words.iter().all(|word| {
+                word.chars()
+                    .all(|c| c.is_ascii_digit() || "*,/-".contains(c))
+            })
+        {
+            return Cron::parse(&words)
+                .map(Rule::Cron)
+                .map_err(|err| anyhow!("`{input}`: {err:#}"));
+        }
+        Self::parse_words(&words).map_err(|err| anyhow!("`{input}`: {err:#}"))
+    }
+
+    fn parse_words(words: &[&str]) -> Result<Self> {
+        let mut words = words;
+        if let ["every", rest @ ..] = words {
+            words = rest;
+        }
+
+        if let Some((interval, mut rest)) = parse_interval(words) {
+            let mut from = NaiveTime::MIN;
+            let mut to = NaiveTime::from_hms_opt(23, 59, 59).unwrap_or(NaiveTime::MIN);
+            let mut days = Weekdays::ALL;
+            loop {
+                match rest {
+                    ["between", start, "and", end, tail @ ..] => {
+                        from = wallclock::parse_clock(start)?;
+                        to = wallclock::parse_clock(end)?;
+                        if from >= to {
+                            bail!("`between {start} and {end}` must run forwards within a day");
+                        }
+                        rest = tail;
+                    }
+                    ["on", list, tail @ ..] => {
+                        days = Weekdays::parse(list)?;
+                        rest = tail;
+                    }
+                    [word, ..] => {
+                        bail!("unexpected `{word}`; expected `between A and B` or `on DAYS`")
+                    }
+                    [] => break,
+                }
+            }
+            return Ok(Rule::Every {
+                interval,
+                from,
+                to,
+                days,
+            });
+        }
+
+        let (days, rest) = match words {
+            [first, rest @ ..] if Weekdays::parse(first).is_ok() => (Weekdays::parse(first)?, rest),
+            _ => (Weekdays::ALL, words),
+        };
+        let rest = match rest {
+            ["at", rest @ ..] => rest,
+            rest => rest,
+        };
+        match rest {
+            [clock] => Ok(Rule::At {
+                days,
+                time: wallclock::parse_clock(clock)?,
+            }),
+            _ => bail!(
+                "expected e.g. `weekdays 09:55`, `every 45m between 09:00 and 18:00` or a cron expression"
+            ),
+        }
+    }
+
+    /// The first occurrence strictly after `after`, in `after`'s timezone.
+    pub fn next_after<Tz: TimeZone>(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let zone = after.timezone();
+        let start = after.date_naive();
+        (0..SEARCH_DAYS)
+            .filter_map(|offset| start.checked_add_days(Days::new(offset)))
+            .flat_map(|date| {
+                let zone = zone.clone();
+                self.times_on(date)
+                    .into_iter()
+                    .filter_map(move |time| wallclock::resolve(&zone, date.and_time(time)).ok())
+            })
+            .find(|moment| *moment > after)
+    }
+
+    /// The local times the rule fires at on `date`, earliest first.
+    fn times_on(&self, date: NaiveDate) -> Vec<NaiveTime> {
+        match self {
+            Rule::At { days, time } if days.contains(date) => vec![*time],
+            Rule::Every {
+                interval,
+                from,
+                to,
+                days,
+            } if days.contains(date) => {
+                let mut times = vec![*from];
+                let mut time = *from;
+                loop {
+                    let (next, wrapped) =
+                        time.overflowing_add_signed(TimeDelta::seconds(*interval));
+                    if wrapped != 0 || next > *to {
+                        break times;
+                    }
+                    times.push(next);
+                    time = next;
+                }
+            }
+            Rule::Cron(cron) if cron.matches_day(date) => (0..24)
+                .filter(|hour| cron.hours & (1 << hour) != 0)
+                .flat_map(|hour| {
+                    (0..60)
+                        .filter(|minute| cron.minutes & (1 << minute) != 0)
+                        .filter_map(move |minute| NaiveTime::from_hms_opt(hour, minute, 0))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Weekdays {
+    const ALL: Self = Self([true; 7]);
+    const NAMES: [&'static str; 7] = [
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+
+    /// `daily`, `weekdays`, `weekends`, or day names and ranges such as `mon,wed-fri`.
+    fn parse(text: &str) -> Result<Self> {
+        match text {
+            "day" | "days" | "daily" | "everyday" => return Ok(Self::ALL),
+            "weekday" | "weekdays" => {
+                return Ok(Self([true, true, true, true, true, false, false]))
+            }
+            "weekend" | "weekends" => {
+                return Ok(Self([false, false, false, false, false, true, true]))
+            }
+            _ => {}
+        }
+        let mut days = [false; 7];
+        for part in text.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (Self::index(first)?, Self::index(last)?),
+                None => (Self::index(part)?, Self::index(part)?),
+            };
+            // Ranges may wrap past Sunday: `fri-mon`.
+            let mut day = first;
+            loop {
+                days[day] = true;
+                if day == last {
+                    break;
+                }
+                day = (day + 1) % 7;
+            }
+        }
+        Ok(Self(days))
+    }
+
+    fn index(name: &str) -> Result<usize> {
+        Self::NAMES
+            .iter()
+            .position(|full| name.len() >= 3 && full.starts_with(name))
+            .ok_or_else(|| anyhow!("`{name}` is not a day of the week"))
+    }
+
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.0[date.weekday().num_days_from_monday() as usize]
+    }
+}
+
+impl Cron {
+    fn parse(fields: &[&str]) -> Result<Self> {
+        let [minutes, hours, days, months, weekdays] = fields else {
+            bail!("a cron expression has five fields");
+        };
+        let mut weekday_bits = cron_field(weekdays, 0, 7).context("day of week")?;
+        // Both 0 and 7 are Sunday.
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits = (weekday_bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: cron_field(minutes, 0, 59).context("minute")?,
+            hours: cron_field(hours, 0, 23).context("hour")?,
+            days: cron_field(days, 1, 31).context("day of month")?,
+            months: cron_field(months, 1, 12).context("month")?,
+            weekdays: weekday_bits,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+/// One cron field (`*`, `5`, `1-5`, `*/15`, `9-17/2`, comma-separated) as a
+/// bit set over `min..=max`.
+fn cron_field(text: &str, min: u32, max: u32) -> Result<u64> {
+    let mut bits = 0;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)),
+            None => (part, Some(1)),
+        };
+        let step = step.ok_or_else(|| anyhow!("bad step in `{part}`"))?;
+        let number = |text: &str| -> Result<u32> {
+            text.parse::<u32>()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(|| anyhow!("`{text}` is outside {min}-{max}"))
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (number(first)?, number(last)?),
+            // `5/15` runs from 5 to the end of the range.
+            None if part.contains('/') => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if first > last {
+            bail!("`{part}` runs backwards");
+        }
+        for value in (first..=last).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// A leading interval such as `45m` or `45 minutes`, and the words after it.
+/// Clock times are left alone: `9:30` is a time of day here, not a duration.
+fn parse_interval<'a>(words: &'a [&'a str]) -> Option<(i64, &'a [&'a str])> {
+    let (number, rest) = words.split_first()?;
+    if number.contains(':') {
+        return None;
+    }
+    let unit = match rest.first() {
+        Some(&("second" | "seconds" | "sec" | "secs")) => Some("s"),
+        Some(&("minute" | "minutes" | "min" | "mins")) => Some("m"),
+        Some(&("hour" | "hours")) => Some("h"),
+        _ => None,
+    };
+    match unit {
+        Some(unit) => Some((
+            duration::parse(&format!("{number}{unit}")).ok()?,
+            &rest[1..],
+        )),
+        None => Some((duration::parse(number).ok()?, rest)),
+    }
+}
+
+fn path() -> PathBuf {
+    persist::state_dir().join("schedules.json")
+}
+
+fn load() -> Result<Vec<Schedule>> {
+    let path = path();
+    match fs::read(&path) {
+        Ok(data) => {
+            serde_json::from_slice(&data).with_context(|| format!("reading {}", path.display()))
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err).with_context(|| format!("reading {}", path.display())),
+    }
+}
+
+fn save(schedules: &[Schedule]) -> Result<()> {
+    let path = path();
+    let dir = persist::state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    // Write then rename, as the daemon may be reading it.
+    let tmp = dir.join(".schedules.json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(schedules)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Load the schedules, `change` them and save them again, holding a lock so
+/// that the daemon and `tytimer schedule` don't undo each other's changes.
+/// Nothing is saved if `change` fails.
+fn update<T>(change: impl FnOnce(&mut Vec<Schedule>) -> Result<T>) -> Result<T> {
+    let dir = persist::state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let path = dir.join("schedules.lock");
+    let lock = File::create(&path).with_context(|| format!("opening {}", path.display()))?;
+    // Released when `lock` is dropped.
+    if unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("locking the schedules");
+    }
+    let mut schedules = load()?;
+    let result = change(&mut schedules)?;
+    save(&schedules)?;
+    Ok(result)
+}
+
+/// `tytimer schedule add`
+pub fn add(
+    rule: &str,
+    label: Option<String>,
+    message: Option<String>,
+    lead: Option<i64>,
+) -> Result<()> {
+    let parsed = Rule::parse(rule)?;
+    let now = Local::now();
+    let next = parsed
+        .next_after(now)
+        .ok_or_else(|| anyhow!("`{rule}` never fires"))?;
+
+    let id = update(|schedules| {
+        let id = schedules
+            .iter()
+            .map(|schedule| schedule.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        schedules.push(Schedule {
+            id,
+            rule: rule.trim().to_string(),
+            label,
+            message,
+            lead: lead.unwrap_or(0),
+            armed_from: now.timestamp(),
+            next: None,
+        });
+        Ok(id)
+    })?;
+
+    println!(
+        "✅ Schedule {id} added; next at {}",
+        wallclock::format_local(next.into())
+    );
+    if ipc::request(&ipc::daemon_socket(), &Request::List).is_err() {
+        eprintln!("Note: schedules only fire while `tytimer daemon` is running.");
+    }
+    Ok(())
+}
+
+/// `tytimer schedule list`
+pub fn list(json: bool) -> Result<()> {
+    let schedules = load()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&schedules)?);
+        return Ok(());
+    }
+    if schedules.is_empty() {
+        println!("No schedules.");
+        return Ok(());
+    }
+
+    println!("{:<4} {:<36} {:<16} {:<10}", "ID", "RULE", "LABEL", "NEXT");
+    let now = Local::now();
+    for schedule in &schedules {
+        let next = Rule::parse(&schedule.rule)
+            .ok()
+            .and_then(|rule| rule.next_after(now))
+            .map(|next| wallclock::format_local(next.into()))
+            .unwrap_or_else(|| "-".into());
+        println!(
+            "{:<4} {:<36} {:<16} {:<10}",
+            schedule.id,
+            schedule.rule,
+            schedule.label.as_deref().unwrap_or("-"),
+            next
+        );
+    }
+    Ok(())
+}
+
+/// `tytimer schedule remove`
+pub fn remove(id: u32) -> Result<()> {
+    update(|schedules| {
+        let before = schedules.len();
+        schedules.retain(|schedule| schedule.id != id);
+        if schedules.len() == before {
+            bail!("no schedule with id {id}");
+        }
+        Ok(())
+    })?;
+    println!("Removed schedule {id}");
+    Ok(())
+}
+
+/// Start a timer in `host` for each schedule occurrence as it comes round.
+/// The schedules file is re-read whenever it changes, so `tytimer schedule`
+/// takes effect without restarting the daemon.
+pub fn run(host: &Rc<Host>) {
+    let host = Rc::clone(host);
+    let schedules = RefCell::new(Vec::new());
+    let modified = Cell::new(None::<SystemTime>);
+    glib::timeout_add_local(Duration::from_secs(1), move || {
+        let changed = fs::metadata(path()).and_then(|meta| meta.modified()).ok();
+        if changed != modified.get() {
+            match load() {
+                Ok(loaded) => *schedules.borrow_mut() = armed(loaded),
+                Err(err) => eprintln!("Ignoring schedules: {err:#}"),
+            }
+            modified.set(changed);
+        }
+
+        let mut schedules = schedules.borrow_mut();
+        let fired: Vec<(u32, i64)> = schedules
+            .iter_mut()
+            .filter_map(|schedule| {
+                fire(&host, schedule).then_some((schedule.id, schedule.armed_from))
+            })
+            .collect();
+        if !fired.is_empty() {
+            // Apply the moves to the file as it is now, not to our copy, which
+            // would bring back schedules removed since it was read.
+            let saved = update(|stored| {
+                for schedule in stored.iter_mut() {
+                    if let Some((_, armed_from)) = fired.iter().find(|(id, _)| *id == schedule.id) {
+                        schedule.armed_from = *armed_from;
+                    }
+                }
+                Ok(stored.clone())
+            });
+            match saved {
+                Ok(saved) => *schedules = armed(saved),
+                Err(err) => eprintln!("Failed to save schedules: {err:#}"),
+            }
+            modified.set(fs::metadata(path()).and_then(|meta| meta.modified()).ok());
+        }
+        glib::ControlFlow::Continue
+    });
+}
+
+/// `schedules`, each [armed](Schedule::arm).
+fn armed(mut schedules: Vec<Schedule>) -> Vec<Schedule> {
+    schedules.iter_mut().for_each(Schedule::arm);
+    schedules
+}
+
+/// Start `schedule`'s timer if its next occurrence is (within its lead) due;
+/// `true` when the schedule moved on and needs saving.
+fn fire(host: &Rc<Host>, schedule: &mut Schedule) -> bool {
+    let Some(next) = schedule.next else {
+        return false;
+    };
+    let now = Local::now();
+    if next.timestamp() - schedule.lead > now.timestamp() {
+        return false;
+    }
+
+    if now.timestamp() - next.timestamp() > GRACE_SECONDS {
+        eprintln!(
+            "Skipped schedule {} due at {}: the daemon was not running",
+            schedule.id,
+            wallclock::format_local(next.into())
+        );
+        schedule.armed_from = now.timestamp() - GRACE_SECONDS;
+        schedule.arm();
+        return true;
+    }
+
+    let spec = TimerSpec {
+        at: Some(next.timestamp() as u64),
+        label: schedule
+            .label
+            .clone()
+            .or_else(|| Some(schedule.rule.clone())),
+        message: schedule.message.clone(),
+        alarm: config::load_or_default().alarm.options(),
+        ..Default::default()
+    };
+    if let Err(err) = host.start(&spec) {
+        eprintln!("Failed to start scheduled timer {}: {err}", schedule.id);
+    }
+    schedule.armed_from = next.timestamp();
+    schedule.arm();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{Europe::Berlin, Tz};
+
+    /// A time in a zone with DST, whatever the machine's own zone is.
+    fn berlin(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        Berlin
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    /// The next `count` occurrences of `rule` after `after`.
+    fn next(rule: &str, after: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let rule = Rule::parse(rule).unwrap();
+        std::iter::successors(Some(after), |after| rule.next_after(*after))
+            .skip(1)
+            .take(count)
+            .collect()
+    }
+
+    #[test]
+    fn weekdays_at() {
+        // A Saturday.
+        let after = berlin(2026, 10, 17, 10, 0);
+        assert_eq!(
+            next("weekdays 09:55", after, 2),
+            [berlin(2026, 10, 19, 9, 55), berlin(2026, 10, 20, 9, 55)]
+        );
+    }
+
+    #[test]
+    fn every_between() {
+        let rule = "every 45 minutes between 09:00 and 18:00";
+        assert_eq!(
+            next(rule, berlin(2026, 10, 17, 9, 30), 2),
+            [berlin(2026, 10, 17, 9, 45), berlin(2026, 10, 17, 10, 30)]
+        );
+        // 09:00 plus twelve intervals lands on 18:00 exactly.
+        assert_eq!(
+            next(rule, berlin(2026, 10, 17, 17, 50), 2),
+            [berlin(2026, 10, 17, 18, 0), berlin(2026, 10, 18, 9, 0)]
+        );
+    }
+
+    #[test]
+    fn cron_steps_and_ranges() {
+        let rule = "*/15 9-17 * * 1-5";
+        // Friday evening to Monday morning.
+        assert_eq!(
+            next(rule, berlin(2026, 10, 16, 17, 50), 2),
+            [berlin(2026, 10, 19, 9, 0), berlin(2026, 10, 19, 9, 15)]
+        );
+        assert_eq!(
+            next(rule, berlin(2026, 10, 19, 17, 45), 1),
+            [berlin(2026, 10, 20, 9, 0)]
+        );
+    }
+
+    #[test]
+    fn cron_day_of_month_or_day_of_week() {
+        // The 13th (a Tuesday) or any Friday.
+        assert_eq!(
+            next("0 12 13 * 5", berlin(2026, 10, 10, 13, 0), 3),
+            [
+                berlin(2026, 10, 13, 12, 0),
+                berlin(2026, 10, 16, 12, 0),
+                berlin(2026, 10, 23, 12, 0)
+            ]
+        );
+        // Only one day field restricted: just that one.
+        assert_eq!(
+            next("0 12 13 * *", berlin(2026, 10, 13, 13, 0), 1),
+            [berlin(2026, 11, 13, 12, 0)]
+        );
+        // Sunday as 7.
+        assert_eq!(
+            next("0 12 * * 7", berlin(2026, 10, 17, 13, 0), 1),
+            [berlin(2026, 10, 18, 12, 0)]
+        );
+    }
+
+    #[test]
+    fn day_ranges_wrap_past_sunday() {
+        // From a Monday: Friday to Monday, skipping Tuesday to Thursday.
+        assert_eq!(
+            next("fri-mon 08:00", berlin(2026, 10, 19, 9, 0), 4),
+            [
+                berlin(2026, 10, 23, 8, 0),
+                berlin(2026, 10, 24, 8, 0),
+                berlin(2026, 10, 25, 8, 0),
+                berlin(2026, 10, 26, 8, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        for rule in [
+            "0 17-9 * * *",
+            "*/0 * * * *",
+            "0 9-17/0 * * *",
+            "61 * * * *",
+            "0 12 * * 8",
+            "* * * *",
+            "every 45m between 18:00 and 09:00",
+            "every 45m on someday",
+            "someday 09:00",
+            "weekdays",
+        ] {
+            assert!(Rule::parse(rule).is_err(), "`{rule}` should not parse");
+        }
+    }
+
+    #[test]
+    fn skips_over_a_dst_gap() {
+        // Clocks in Berlin go from 02:00 straight to 03:00 on 29 March 2026.
+        assert_eq!(
+            next("daily 02:30", berlin(2026, 3, 28, 3, 0), 2),
+            [berlin(2026, 3, 29, 3, 0), berlin(2026, 3, 30, 2, 30)]
+        );
+    }
+}
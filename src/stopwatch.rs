@@ -0,0 +1,106 @@
+use crate::persist;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// A stopwatch (`tytimer stopwatch`): the timer counts up from zero and
+/// records laps along the way.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stopwatch {
+    /// Elapsed milliseconds at the end of each lap, oldest first.
+    pub laps: Vec<i64>,
+}
+
+/// One recorded lap.
+#[derive(Debug, Clone, Copy)]
+pub struct Lap {
+    /// Counting from 1.
+    pub number: usize,
+    /// Length of this lap alone.
+    pub split_ms: i64,
+    /// Elapsed time when the lap ended.
+    pub total_ms: i64,
+}
+
+impl Stopwatch {
+    /// End the current lap at `elapsed_ms`.
+    pub fn lap(&mut self, elapsed_ms: i64) {
+        self.laps.push(elapsed_ms);
+    }
+
+    pub fn laps(&self) -> impl Iterator<Item = Lap> + '_ {
+        let starts = std::iter::once(0).chain(self.laps.iter().copied());
+        self.laps
+            .iter()
+            .zip(starts)
+            .enumerate()
+            .map(|(i, (&total_ms, start))| Lap {
+                number: i + 1,
+                split_ms: total_ms - start,
+                total_ms,
+            })
+    }
+
+    /// Lines for the window's lap list, newest first.
+    pub fn describe_laps(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .laps()
+            .map(|lap| {
+                format!(
+                    "Lap {}  {}  ({})",
+                    lap.number,
+                    format_ms(lap.split_ms),
+                    format_ms(lap.total_ms)
+                )
+            })
+            .collect();
+        lines.reverse();
+        lines
+    }
+
+    /// Write the laps, closed off by a final one ending at `elapsed_ms`, to a
+    /// CSV file under the state directory and return its path.
+    pub fn export(&self, label: &str, elapsed_ms: i64) -> Result<PathBuf> {
+        let mut all = self.clone();
+        if all.laps.last() != Some(&elapsed_ms) {
+            all.lap(elapsed_ms);
+        }
+        let mut csv = String::from("lap,lap_seconds,total_seconds\n");
+        for lap in all.laps() {
+            let _ = writeln!(
+                csv,
+                "{},{:.2},{:.2}",
+                lap.number,
+                lap.split_ms as f64 / 1000.0,
+                lap.total_ms as f64 / 1000.0
+            );
+        }
+
+        let dir = persist::state_dir().join("laps");
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        let name: String = label
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let path = dir.join(format!("{name}-{stamp}.csv"));
+        fs::write(&path, csv).with_context(|| format!("writing {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Elapsed time to the hundredth, e.g. `1:02.35` or `1:01:02.35`.
+pub fn format_ms(ms: i64) -> String {
+    let ms = ms.max(0);
+    let hundredths = ms / 10 % 100;
+    let seconds = ms / 1000 % 60;
+    let minutes = ms / 60_000 % 60;
+    let hours = ms / 3_600_000;
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}.{hundredths:02}")
+    } else {
+        format!("{minutes}:{seconds:02}.{hundredths:02}")
+    }
+}
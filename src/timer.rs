@@ -0,0 +1,372 @@
+use crate::audio::AlarmOptions;
+use crate::duration;
+use crate::interval::Intervals;
+use crate::pomodoro::Pomodoro;
+use crate::stopwatch::Stopwatch;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A reading of `CLOCK_BOOTTIME` in milliseconds.
+///
+/// Unlike `CLOCK_MONOTONIC` (and therefore `std::time::Instant`), boottime keeps
+/// counting while the machine is suspended, so a deadline expressed on it is
+/// reached at the right wall-clock moment even across a laptop lid close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BootTime(i64);
+
+impl BootTime {
+    pub fn now() -> Self {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // CLOCK_BOOTTIME cannot fail on Linux >= 2.6.39; fall back to the
+        // monotonic clock just in case we are somewhere it is missing.
+        let ok = unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) } == 0
+            || unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) } == 0;
+        if !ok {
+            return Self(0);
+        }
+        let since_boot = Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+        Self(since_boot.as_millis() as i64)
+    }
+
+    fn add_ms(self, ms: i64) -> Self {
+        Self(self.0.saturating_add(ms))
+    }
+
+    fn ms_until(self, later: Self) -> i64 {
+        later.0 - self.0
+    }
+}
+
+/// The absolute moment a running timer ends.
+///
+/// Relative timers ("25 minutes") count against boottime so that changes to the
+/// realtime clock cannot stretch or shrink them. Timers aimed at a wall-clock
+/// moment ("at 14:30") count against the realtime clock instead, which keeps
+/// them on target if the clock is corrected while they run; the boottime
+/// reading is still tracked so either view is available.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    boot: BootTime,
+    wall: SystemTime,
+    wall_anchored: bool,
+}
+
+impl Deadline {
+    /// A deadline `ms` milliseconds from now.
+    pub fn in_ms(ms: i64) -> Self {
+        Self {
+            boot: BootTime::now().add_ms(ms),
+            wall: shift_wall(SystemTime::now(), ms),
+            wall_anchored: false,
+        }
+    }
+
+    /// A deadline at a wall-clock moment.
+    pub fn at(wall: SystemTime) -> Self {
+        Self {
+            boot: BootTime::now().add_ms(ms_between(SystemTime::now(), wall)),
+            wall,
+            wall_anchored: true,
+        }
+    }
+
+    fn shifted(&self, ms: i64) -> Self {
+        Self {
+            boot: self.boot.add_ms(ms),
+            wall: shift_wall(self.wall, ms),
+            wall_anchored: self.wall_anchored,
+        }
+    }
+
+    /// Milliseconds left until the deadline; negative once it has passed.
+    pub fn remaining_ms(&self) -> i64 {
+        if self.wall_anchored {
+            ms_between(SystemTime::now(), self.wall)
+        } else {
+            BootTime::now().ms_until(self.boot)
+        }
+    }
+
+    /// The wall-clock moment the deadline falls on.
+    pub fn wall(&self) -> SystemTime {
+        if self.wall_anchored {
+            self.wall
+        } else {
+            shift_wall(SystemTime::now(), self.remaining_ms())
+        }
+    }
+}
+
+fn ms_between(from: SystemTime, to: SystemTime) -> i64 {
+    match to.duration_since(from) {
+        Ok(ahead) => ahead.as_millis() as i64,
+        Err(err) => -(err.duration().as_millis() as i64),
+    }
+}
+
+/// `wall` moved by `ms`, or left where it is if that is past what the clock
+/// can represent.
+fn shift_wall(wall: SystemTime, ms: i64) -> SystemTime {
+    let by = Duration::from_millis(ms.unsigned_abs());
+    let shifted = if ms >= 0 {
+        wall.checked_add(by)
+    } else {
+        wall.checked_sub(by)
+    };
+    shifted.unwrap_or(wall)
+}
+
+/// `seconds` in milliseconds, held at the limits of `i64` rather than
+/// overflowing.
+fn to_ms(seconds: i64) -> i64 {
+    seconds.saturating_mul(1000)
+}
+
+/// Everything needed to start a timer, in a form that can be handed to another
+/// process (a detached child or the daemon).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimerSpec {
+    /// Length in seconds, for a relative timer.
+    pub seconds: Option<i64>,
+    /// Unix time to end at, for a wall-clock timer (`--at`).
+    pub at: Option<u64>,
+    /// Name shown in the tray and window, e.g. "pasta".
+    pub label: Option<String>,
+    /// Text shown in the alarm window when the timer goes off.
+    pub message: Option<String>,
+    pub alarm: AlarmOptions,
+    /// Set for `tytimer pomodoro`: the timer runs the session's phases in turn.
+    pub pomodoro: Option<Pomodoro>,
+    /// Set for `tytimer intervals`: work/rest rounds, ringing after the last.
+    pub intervals: Option<Intervals>,
+    /// Set for `tytimer stopwatch`: counts up from zero and never rings.
+    pub stopwatch: Option<Stopwatch>,
+    /// Where a timer restored from disk had got to.
+    pub restore: Option<Snapshot>,
+}
+
+impl TimerSpec {
+    pub fn state(&self) -> Result<TimerState> {
+        if let Some(snapshot) = &self.restore {
+            let mut state = TimerState::restore(snapshot, self.at);
+            // A stopwatch's deadline is the moment it started, never a missed end.
+            if self.stopwatch.is_some() {
+                state.missed = None;
+            }
+            return Ok(state);
+        }
+        if self.stopwatch.is_some() {
+            return Ok(TimerState::new(0));
+        }
+        match (self.seconds, self.at) {
+            (Some(seconds), _) if seconds > duration::MAX_SECONDS => {
+                bail!("timer length {seconds}s is longer than the maximum of 7 days")
+            }
+            (Some(seconds), _) if seconds > 0 => Ok(TimerState::new(seconds)),
+            (Some(seconds), _) => bail!("timer length must be positive, got {seconds}s"),
+            (None, Some(at)) => UNIX_EPOCH
+                .checked_add(Duration::from_secs(at))
+                .map(TimerState::until)
+                .ok_or_else(|| anyhow!("end time {at} is out of range")),
+            (None, None) => match (&self.pomodoro, &self.intervals) {
+                (Some(pomodoro), _) => Ok(TimerState::new(pomodoro.phase_seconds())),
+                (None, Some(intervals)) => Ok(TimerState::new(intervals.phase_seconds())),
+                (None, None) => bail!("timer has neither a length nor an end time"),
+            },
+        }
+    }
+}
+
+/// The progress of a timer, saved so it can be restored in a later session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub original: i64,
+    /// Unix milliseconds the timer ends at, while running.
+    pub ends_at_ms: Option<i64>,
+    /// Milliseconds that were left, while paused.
+    pub paused_ms: Option<i64>,
+    pub snoozes: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimerState {
+    pub original: i64,
+    deadline: Deadline,
+    /// Time that was left when the timer was paused; `None` while running.
+    paused_ms: Option<i64>,
+    pub alarm_triggered: bool,
+    /// Set when the timer was started for a wall-clock moment (`--at`);
+    /// dropped once a pause or snooze moves the end elsewhere.
+    target: Option<SystemTime>,
+    pub snoozes: u32,
+    /// When the deadline passed, for a timer restored from disk too late.
+    pub missed: Option<SystemTime>,
+}
+
+impl TimerState {
+    pub fn new(total_seconds: i64) -> Self {
+        Self {
+            original: total_seconds,
+            deadline: Deadline::in_ms(to_ms(total_seconds)),
+            paused_ms: None,
+            alarm_triggered: false,
+            target: None,
+            snoozes: 0,
+            missed: None,
+        }
+    }
+
+    /// A timer that ends at the wall-clock moment `target`.
+    pub fn until(target: SystemTime) -> Self {
+        let deadline = Deadline::at(target);
+        Self {
+            original: ceil_seconds(deadline.remaining_ms()).max(1),
+            deadline,
+            paused_ms: None,
+            alarm_triggered: false,
+            target: Some(target),
+            snoozes: 0,
+            missed: None,
+        }
+    }
+
+    /// Pick a saved timer back up. The deadline is taken from the wall clock,
+    /// as boottime readings do not survive a reboot; a deadline that passed
+    /// while nothing was running marks the timer as missed, and it will ring
+    /// straight away.
+    pub fn restore(snapshot: &Snapshot, at: Option<u64>) -> Self {
+        let (deadline, paused_ms) = match (snapshot.paused_ms, snapshot.ends_at_ms) {
+            (Some(left), _) => (Deadline::in_ms(left), Some(left)),
+            (None, Some(ends_at_ms)) => (
+                Deadline::at(UNIX_EPOCH + Duration::from_millis(ends_at_ms.max(0) as u64)),
+                None,
+            ),
+            (None, None) => (Deadline::in_ms(0), None),
+        };
+        // Still aiming for the original `--at` moment unless it was moved.
+        let target = at
+            .filter(|at| snapshot.ends_at_ms == i64::try_from(*at).ok().map(to_ms))
+            .map(|at| UNIX_EPOCH + Duration::from_secs(at));
+        Self {
+            original: snapshot.original,
+            deadline,
+            paused_ms,
+            alarm_triggered: false,
+            target,
+            snoozes: snapshot.snoozes,
+            missed: (paused_ms.is_none() && deadline.remaining_ms() <= 0).then(|| deadline.wall()),
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let ends_at_ms = self
+            .running()
+            .then(|| ms_between(UNIX_EPOCH, self.deadline.wall()));
+        Snapshot {
+            original: self.original,
+            ends_at_ms,
+            paused_ms: self.paused_ms,
+            snoozes: self.snoozes,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.paused_ms.is_none()
+    }
+
+    pub fn remaining_ms(&self) -> i64 {
+        self.paused_ms.unwrap_or_else(|| self.deadline.remaining_ms())
+    }
+
+    /// Whole seconds left, rounded up so a fresh 25:00 timer shows 25:00 and
+    /// reaches 0:00 exactly at the deadline. Negative once overdue.
+    pub fn remaining(&self) -> i64 {
+        ceil_seconds(self.remaining_ms())
+    }
+
+    /// Whole seconds the alarm has been left going past the end; `0` until
+    /// it goes off.
+    pub fn overtime(&self) -> i64 {
+        if self.alarm_triggered {
+            (-self.remaining()).max(0)
+        } else {
+            0
+        }
+    }
+
+    /// Milliseconds since the timer started, less time spent paused; what a
+    /// stopwatch shows.
+    pub fn elapsed_ms(&self) -> i64 {
+        to_ms(self.original).saturating_sub(self.remaining_ms())
+    }
+
+    /// The wall-clock moment a `--at` timer is aiming for, while it still is.
+    pub fn target(&self) -> Option<SystemTime> {
+        self.target.filter(|_| self.running())
+    }
+
+    /// Whether the deadline has passed and the alarm still needs firing.
+    pub fn is_due(&self) -> bool {
+        self.running() && !self.alarm_triggered && self.remaining_ms() <= 0
+    }
+
+    pub fn pause(&mut self) {
+        if self.paused_ms.is_none() {
+            self.paused_ms = Some(self.deadline.remaining_ms());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(left) = self.paused_ms.take() {
+            self.deadline = Deadline::in_ms(left);
+            self.target = None;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if self.running() {
+            self.pause();
+        } else {
+            self.resume();
+        }
+    }
+
+    /// Start counting down `seconds` afresh from now and re-arm the alarm.
+    pub fn restart_with(&mut self, seconds: i64) {
+        self.deadline = Deadline::in_ms(to_ms(seconds));
+        self.paused_ms = None;
+        self.alarm_triggered = false;
+        self.target = None;
+        self.missed = None;
+    }
+
+    /// Silence a ringing timer and ring again in `seconds`.
+    pub fn snooze(&mut self, seconds: i64) {
+        self.restart_with(seconds);
+        self.snoozes += 1;
+    }
+
+    /// Move the end of the timer by `seconds`, whether running or paused.
+    /// Pushing an expired timer back into the future re-arms its alarm.
+    pub fn shift(&mut self, seconds: i64) {
+        let ms = to_ms(seconds);
+        match self.paused_ms.as_mut() {
+            Some(left) => *left = left.saturating_add(ms),
+            None => self.deadline = self.deadline.shifted(ms),
+        }
+        self.target = None;
+        self.missed = None;
+        if self.remaining_ms() > 0 {
+            self.alarm_triggered = false;
+        }
+    }
+}
+
+fn ceil_seconds(ms: i64) -> i64 {
+    (ms + 999).div_euclid(1000)
+}
@@ -1,39 +1,147 @@
+use crate::config::{Config, Snooze, TrayClick, TrayConfig};
+use crate::icon::{RingCache, Tone};
 use anyhow::{anyhow, Result};
 use ksni::blocking::{Handle, TrayMethods};
-use ksni::menu::{MenuItem, StandardItem};
-use ksni::{Category, Status, ToolTip, Tray};
+use ksni::menu::{MenuItem, StandardItem, SubMenu};
+use ksni::{Category, Icon, OfflineReason, Orientation, Status, ToolTip, Tray};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+
+/// Scrolling moves the end at most once this often.
+const SCROLL_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Debug, Clone, Copy)]
 pub enum TrayAction {
     ToggleRunning,
+    Pause,
+    Resume,
+    /// Move the end of the timer by this many seconds.
+    Extend(i64),
+    /// Silence the alarm and ring again in this many seconds.
+    Snooze(i64),
+    /// Snooze by one of the configured snooze lengths.
+    SnoozeBy(Snooze),
+    /// Start the timer (or the current phase) over from its full length.
+    Restart,
+    /// End the current pomodoro phase and start the next.
+    SkipPhase,
+    /// Record a lap on a stopwatch.
+    Lap,
     ShowAlarm,
+    /// The desktop notification for the alarm could not be shown.
+    NotificationFailed,
     Quit,
 }
 
+/// What the tray shows about its timer, sent over on every tick.
+#[derive(Debug, Clone)]
+pub struct TrayStatus {
+    /// Time left, or time elapsed on a stopwatch, e.g. `4:59`.
+    pub time: String,
+    /// When a `--at` timer ends, e.g. `14:30`.
+    pub target: Option<String>,
+    /// When the timer will end if left running, e.g. `14:30`.
+    pub ends: Option<String>,
+    pub running: bool,
+    /// Pomodoro phase or interval round, e.g. `Work 2/4`.
+    pub phase: Option<String>,
+    /// Counting up rather than down.
+    pub stopwatch: bool,
+    /// The alarm went off and is still going; `time` reads e.g. `+3:12 over`.
+    pub overtime: bool,
+    /// A `--warn` threshold has passed: the end is near.
+    pub warning: bool,
+    /// Share of the time left, from 1 down to 0, for the progress icon.
+    pub progress: f64,
+    /// Minutes left (or elapsed, or over) for the icon's digits.
+    pub minutes: i64,
+    /// More than a minute is left, so taking one off won't end the timer.
+    pub can_shorten: bool,
+}
+
+impl Default for TrayStatus {
+    fn default() -> Self {
+        Self {
+            time: "--:--".into(),
+            target: None,
+            ends: None,
+            running: true,
+            phase: None,
+            stopwatch: false,
+            overtime: false,
+            warning: false,
+            progress: 1.0,
+            minutes: 0,
+            can_shorten: false,
+        }
+    }
+}
+
 pub struct TrayHandle {
     handle: Option<Handle<TimerTray>>,
+    /// Set while no StatusNotifier host is around to show the icon.
+    offline: Arc<AtomicBool>,
 }
 
 impl TrayHandle {
-    pub fn spawn(action_tx: Sender<TrayAction>) -> Result<Self> {
-        let tray = TimerTray::new(action_tx);
+    pub fn spawn(
+        action_tx: Sender<TrayAction>,
+        label: Option<String>,
+        config: &Config,
+    ) -> Result<Self> {
+        let presets = config.presets.keys().cloned().collect();
+        let offline = Arc::new(AtomicBool::new(false));
+        let tray = TimerTray::new(
+            action_tx,
+            label,
+            config.tray.clone(),
+            presets,
+            config.snooze.clone(),
+            Arc::clone(&offline),
+        );
         let handle = tray.spawn()?;
         Ok(Self {
             handle: Some(handle),
+            offline,
         })
     }
 
     pub fn noop(_action_tx: Sender<TrayAction>) -> Self {
-        Self { handle: None }
+        Self {
+            handle: None,
+            offline: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// A timer shown by the aggregated tray, offline whenever that icon is.
+    pub fn shared(offline: Arc<AtomicBool>) -> Self {
+        Self {
+            handle: None,
+            offline,
+        }
+    }
+
+    /// Stand-in for a tray that failed to start, so the timer is shown some
+    /// other way.
+    pub fn unavailable(err: impl Display) -> Self {
+        warn_offline(err);
+        Self {
+            handle: None,
+            offline: Arc::new(AtomicBool::new(true)),
+        }
     }
 
-    pub fn update_state(&self, label: &str, running: bool) -> Result<()> {
+    /// Whether the icon cannot be seen, for want of a StatusNotifier host.
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    pub fn update_state(&self, status: &TrayStatus) -> Result<()> {
         if let Some(handle) = &self.handle {
-            let result = handle.update(|tray| {
-                tray.remaining_label = label.to_string();
-                tray.running = running;
-            });
+            let result = handle.update(|tray| tray.status = status.clone());
             if result.is_none() {
                 return Err(anyhow!("tray service already stopped"));
             }
@@ -50,17 +158,40 @@ impl TrayHandle {
 
 #[derive(Clone)]
 struct TimerTray {
-    remaining_label: String,
-    running: bool,
+    /// The timer's name, if it was given one.
+    label: Option<String>,
+    config: TrayConfig,
+    /// Preset names offered under "Start preset".
+    presets: Vec<String>,
+    /// Lengths offered under "Snooze".
+    snoozes: Vec<Snooze>,
+    status: TrayStatus,
     action_tx: Sender<TrayAction>,
+    offline: Arc<AtomicBool>,
+    icons: RingCache,
+    /// When a scroll last moved the end.
+    last_scroll: Option<Instant>,
 }
 
 impl TimerTray {
-    fn new(action_tx: Sender<TrayAction>) -> Self {
+    fn new(
+        action_tx: Sender<TrayAction>,
+        label: Option<String>,
+        config: TrayConfig,
+        presets: Vec<String>,
+        snoozes: Vec<Snooze>,
+        offline: Arc<AtomicBool>,
+    ) -> Self {
         Self {
-            remaining_label: "--:--".into(),
-            running: true,
+            label,
+            config,
+            presets,
+            snoozes,
+            status: TrayStatus::default(),
             action_tx,
+            offline,
+            icons: RingCache::default(),
+            last_scroll: None,
         }
     }
 }
@@ -77,62 +208,272 @@ impl Tray for TimerTray {
     }
 
     fn title(&self) -> String {
-        format!("tytimer ({})", self.remaining_label)
+        let name = self.label.as_deref().unwrap_or("tytimer");
+        let status = &self.status;
+        let title = match &status.target {
+            Some(target) => format!("{name} ({} → {target})", status.time),
+            None => format!("{name} ({})", status.time),
+        };
+        match &status.phase {
+            Some(phase) => format!("{title} · {phase}"),
+            None => title,
+        }
     }
 
     fn icon_name(&self) -> String {
-        "alarm-symbolic".into()
+        if self.config.progress_icon {
+            // Left empty so hosts use the pixmap.
+            String::new()
+        } else if !self.status.running {
+            "media-playback-pause-symbolic".into()
+        } else if self.status.warning {
+            "appointment-soon-symbolic".into()
+        } else {
+            "alarm-symbolic".into()
+        }
     }
 
-    fn status(&self) -> Status {
-        if self.running {
-            Status::Active
+    fn icon_pixmap(&self) -> Vec<Icon> {
+        if !self.config.progress_icon {
+            return Vec::new();
+        }
+        let status = &self.status;
+        let tone = if !status.running {
+            Tone::Paused
+        } else if status.overtime {
+            Tone::Ringing
+        } else if status.warning {
+            Tone::Warning
         } else {
+            Tone::Running
+        };
+        let digits = self.config.digits.then(|| match status.minutes {
+            minutes if minutes >= 100 => format!("{}h", minutes / 60),
+            minutes => minutes.to_string(),
+        });
+        self.icons.progress_ring(status.progress, tone, digits.as_deref())
+    }
+
+    fn attention_icon_name(&self) -> String {
+        if self.config.progress_icon {
+            String::new()
+        } else {
+            "alarm-symbolic".into()
+        }
+    }
+
+    fn attention_icon_pixmap(&self) -> Vec<Icon> {
+        self.icon_pixmap()
+    }
+
+    fn status(&self) -> Status {
+        if self.status.overtime || !self.status.running {
             Status::NeedsAttention
+        } else {
+            Status::Active
         }
     }
 
     fn tool_tip(&self) -> ToolTip {
+        let status = &self.status;
         ToolTip {
             icon_name: self.icon_name(),
-            title: "tytimer".into(),
-            description: format!("Remaining {}", self.remaining_label),
+            title: self.label.clone().unwrap_or_else(|| "tytimer".into()),
+            description: match (&status.phase, &status.target) {
+                _ if status.stopwatch => format!("Elapsed {}", status.time),
+                _ if status.overtime => format!("Time's up: {}", status.time),
+                (Some(phase), _) => format!("{phase}: {} left", status.time),
+                _ if status.warning => format!("Ending soon: {} left", status.time),
+                (None, Some(target)) => format!("Remaining {} (ends at {target})", status.time),
+                (None, None) => format!("Remaining {}", status.time),
+            },
             ..Default::default()
         }
     }
 
     fn activate(&mut self, _x: i32, _y: i32) {
-        let _ = self.action_tx.send(TrayAction::ShowAlarm);
+        let action = match self.config.click {
+            TrayClick::ShowWindow => TrayAction::ShowAlarm,
+            TrayClick::TogglePause => TrayAction::ToggleRunning,
+        };
+        let _ = self.action_tx.send(action);
+    }
+
+    /// Middle click pauses or resumes.
+    fn secondary_activate(&mut self, _x: i32, _y: i32) {
+        let _ = self.action_tx.send(TrayAction::ToggleRunning);
+    }
+
+    /// Vertical scrolling adds or takes off a minute, and horizontal
+    /// scrolling five. SNI passes neither modifiers nor the device, so this
+    /// relies on the host: some turn shift+wheel into horizontal scrolling,
+    /// some don't. The spec doesn't say which sign of `delta` is which way
+    /// either; negative adds time here.
+    ///
+    /// Touchpads and smooth wheels send a burst of events per gesture, each
+    /// of which would otherwise be a whole step, so at most one step is taken
+    /// per [`SCROLL_INTERVAL`].
+    fn scroll(&mut self, delta: i32, orientation: Orientation) {
+        if self.status.stopwatch || delta == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_scroll
+            .is_some_and(|last| now.duration_since(last) < SCROLL_INTERVAL)
+        {
+            return;
+        }
+        self.last_scroll = Some(now);
+        let step = match orientation {
+            Orientation::Vertical => 60,
+            Orientation::Horizontal => 5 * 60,
+        };
+        let seconds = if delta < 0 { step } else { -step };
+        let _ = self.action_tx.send(TrayAction::Extend(seconds));
+    }
+
+    fn watcher_online(&self) {
+        self.offline.store(false, Ordering::Relaxed);
+    }
+
+    /// Keep the service running for when the watcher comes back, and have
+    /// the timer show itself meanwhile.
+    fn watcher_offline(&self, reason: OfflineReason) -> bool {
+        match reason {
+            OfflineReason::Error(err) => warn_offline(err),
+            _ => warn_offline("the StatusNotifier watcher went away"),
+        }
+        self.offline.store(true, Ordering::Relaxed);
+        true
     }
 
     fn menu(&self) -> Vec<MenuItem<Self>> {
-        vec![
+        let status = &self.status;
+        let name = self.label.as_deref().unwrap_or("tytimer");
+        let header = match (&status.ends, status.running) {
+            _ if status.stopwatch => format!("{name} · {} elapsed", status.time),
+            _ if status.overtime => format!("{name} · {}", status.time),
+            (Some(ends), true) => format!("{name} · ends {ends}"),
+            _ => format!("{name} · paused, {} left", status.time),
+        };
+        let mut items = vec![
             StandardItem {
-                label: if self.running { "Pause" } else { "Resume" }.into(),
-                activate: Box::new(|this: &mut Self| {
-                    this.running = !this.running;
-                    let _ = this.action_tx.send(TrayAction::ToggleRunning);
-                }),
-                ..Default::default()
-            }
-            .into(),
-            StandardItem {
-                label: "Show Alarm Window".into(),
-                activate: Box::new(|this: &mut Self| {
-                    let _ = this.action_tx.send(TrayAction::ShowAlarm);
-                }),
+                label: header,
+                enabled: false,
                 ..Default::default()
             }
             .into(),
             MenuItem::Separator,
             StandardItem {
-                label: "Quit".into(),
+                label: if status.running { "Pause" } else { "Resume" }.into(),
                 activate: Box::new(|this: &mut Self| {
-                    let _ = this.action_tx.send(TrayAction::Quit);
+                    this.status.running = !this.status.running;
+                    let _ = this.action_tx.send(TrayAction::ToggleRunning);
                 }),
                 ..Default::default()
             }
             .into(),
-        ]
+        ];
+        if status.stopwatch {
+            items.push(action_item("Lap", TrayAction::Lap));
+        } else {
+            items.extend([
+                action_item("+1 min", TrayAction::Extend(60)),
+                action_item("+5 min", TrayAction::Extend(5 * 60)),
+                StandardItem {
+                    enabled: status.can_shorten,
+                    ..action_entry("−1 min", TrayAction::Extend(-60))
+                }
+                .into(),
+            ]);
+        }
+        items.push(action_item("Restart", TrayAction::Restart));
+        if !status.stopwatch && !self.snoozes.is_empty() {
+            items.push(
+                SubMenu {
+                    label: "Snooze".into(),
+                    submenu: self
+                        .snoozes
+                        .iter()
+                        .map(|&snooze| action_item(snooze.to_string(), TrayAction::SnoozeBy(snooze)))
+                        .collect(),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        if status.phase.is_some() {
+            items.push(action_item("Skip Phase", TrayAction::SkipPhase));
+        }
+        items.push(action_item("Show Alarm Window", TrayAction::ShowAlarm));
+        if !self.presets.is_empty() {
+            items.push(
+                SubMenu {
+                    label: "Start preset".into(),
+                    submenu: self
+                        .presets
+                        .iter()
+                        .map(|name| {
+                            let name = name.clone();
+                            StandardItem {
+                                label: name.clone(),
+                                activate: Box::new(move |_: &mut Self| start_preset(&name)),
+                                ..Default::default()
+                            }
+                            .into()
+                        })
+                        .collect(),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        items.extend([
+            MenuItem::Separator,
+            action_item(
+                if status.phase.is_some() { "End Session" } else { "Quit" },
+                TrayAction::Quit,
+            ),
+        ]);
+        items
     }
 }
+
+/// A menu entry that sends `action` to the timer.
+fn action_item(label: impl Into<String>, action: TrayAction) -> MenuItem<TimerTray> {
+    action_entry(label, action).into()
+}
+
+fn action_entry(label: impl Into<String>, action: TrayAction) -> StandardItem<TimerTray> {
+    StandardItem {
+        label: label.into(),
+        activate: Box::new(move |this: &mut TimerTray| {
+            let _ = this.action_tx.send(action);
+        }),
+        ..Default::default()
+    }
+}
+
+/// Run `tytimer <name>` so the new timer is started like any other, in the
+/// daemon or a process of its own.
+fn start_preset(name: &str) {
+    let child = std::env::current_exe()
+        .and_then(|exe| std::process::Command::new(exe).arg(name).spawn());
+    match child {
+        // Reap it once it has handed the timer off.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => eprintln!("Failed to start preset {name}: {err}"),
+    }
+}
+
+/// Say once per process that tray icons cannot be shown; every timer in it
+/// falls back the same way.
+pub fn warn_offline(reason: impl Display) {
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        eprintln!("Tray unavailable: {reason}");
+    });
+}
@@ -1,57 +1,80 @@
 use gtk4::prelude::*;
+use crate::duration;
+use crate::icon::{self, Tone};
+use crate::config::{Anchor, Config, Snooze, ThemeConfig, WindowConfig, WindowLayer};
+use crate::tray::TrayStatus;
 use gtk4::{
-    Application, ApplicationWindow, Box, Button, CssProvider, DrawingArea, Frame, Label,
-    Orientation, Align, STYLE_PROVIDER_PRIORITY_APPLICATION, Scale,
+    Application, ApplicationWindow, Box, Button, CssProvider, DrawingArea, Entry, Frame, Label,
+    Orientation, Align, STYLE_PROVIDER_PRIORITY_APPLICATION, STYLE_PROVIDER_PRIORITY_USER, Scale,
+    ScrolledWindow,
 };
+use gtk4::cairo::{FontSlant, FontWeight};
 use gtk4::{glib, prelude::WidgetExt};
 use gtk4_layer_shell::{Edge, Layer, LayerShell};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::process::Command;
 
+/// Side of the progress ring in the timer window, in pixels.
+const DIAL_SIZE: i32 = 150;
+
 pub struct TimerWindow {
     pub window: ApplicationWindow,
-    pause_buttons: Vec<(u64, Button)>,
+    snooze_buttons: Vec<(Snooze, Button)>,
     stop_button: Button,
+    lap_button: Button,
     close_button: Button,
     remaining_label: Label,
-    original_seconds: i64,
+    notice_label: Label,
+    message_label: Label,
+    phase_label: Label,
+    round_label: Label,
+    laps_label: Label,
+    laps_scroll: ScrolledWindow,
+    dial: DrawingArea,
+    /// What the dial shows: share of time left, colour and the big digits.
+    dial_state: Rc<RefCell<(f64, Tone, String)>>,
+    original_seconds: Cell<i64>,
 }
 
 impl TimerWindow {
-    pub fn new(app: &Application, original_seconds: i64) -> Self {
+    pub fn new(
+        app: &Application,
+        original_seconds: i64,
+        label: Option<&str>,
+        message: Option<&str>,
+        config: &Config,
+    ) -> Self {
         let window = ApplicationWindow::builder()
             .application(app)
-            .title("tytimer")
+            .title(label.unwrap_or("tytimer"))
             .default_width(520)
             .default_height(220)
             .resizable(false)
             .decorated(false)
             .build();
 
-        window.init_layer_shell();
-        window.set_layer(Layer::Top);
-        window.set_anchor(Edge::Top, true);
-        window.set_anchor(Edge::Right, true);
-        window.set_margin(Edge::Top, 16);
-        window.set_margin(Edge::Right, 16);
+        place(&window, &config.window);
 
-        let provider = CssProvider::new();
-        let _ = provider.load_from_data(
+        let display = WidgetExt::display(&window);
+        set_style(
+            &display,
+            "timer",
             "window { background-color: rgba(0, 0, 0, 0.88); color: white; }
              button { border-radius: 6px; padding: 12px 14px; font-weight: 600; }
              button.suggested-action { background: #3b82f6; color: white; }
              button.suggested-action:hover { background: #4b8ffc; }
              frame { border-radius: 10px; }
              label.title { font-size: 24px; font-weight: 800; }
-             label.subtitle { opacity: 0.7; }",
-        );
-
-        let display = WidgetExt::display(&window);
-        gtk4::style_context_add_provider_for_display(
-            &display,
-            &provider,
+             label.subtitle { opacity: 0.7; }
+             label.message { font-size: 18px; }
+             label.round { font-size: 44px; font-weight: 800; }
+             label.laps { font-family: monospace; }
+             label.overtime { color: #f87171; opacity: 1; font-weight: 700; }",
             STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
+        add_theme(&display, &config.theme);
 
         let root_box = Box::builder()
             .orientation(Orientation::Vertical)
@@ -89,13 +112,21 @@ impl TimerWindow {
         header.append(&flag);
 
         let title = Label::builder()
-            .label("TyTimer")
+            .label(label.unwrap_or("TyTimer"))
             .css_classes(vec!["title"])
             .xalign(0.0)
             .yalign(0.5)
             .build();
         header.append(&title);
 
+        // Pomodoro phase, e.g. "Work 2/4"
+        let phase_label = Label::builder()
+            .css_classes(vec!["subtitle"])
+            .yalign(0.5)
+            .visible(false)
+            .build();
+        header.append(&phase_label);
+
         let spacer = Box::new(Orientation::Horizontal, 0);
         spacer.set_hexpand(true);
         header.append(&spacer);
@@ -107,8 +138,58 @@ impl TimerWindow {
         header.append(&close_button);
         root_box.append(&header);
 
+        // Interval round, e.g. "Round 3/8 · Work"
+        let round_label = Label::builder()
+            .css_classes(vec!["round"])
+            .visible(false)
+            .build();
+        root_box.append(&round_label);
+
+        // Progress ring with the time in large digits
+        let dial_state = Rc::new(RefCell::new((1.0, Tone::Running, "--:--".to_string())));
+        let dial = DrawingArea::new();
+        dial.set_content_width(DIAL_SIZE);
+        dial.set_content_height(DIAL_SIZE);
+        dial.set_halign(Align::Center);
+        {
+            let dial_state = Rc::clone(&dial_state);
+            dial.set_draw_func(move |dial, cr, width, height| {
+                let (progress, tone, digits) = &*dial_state.borrow();
+                let size = f64::from(width.min(height));
+                cr.translate((f64::from(width) - size) / 2.0, (f64::from(height) - size) / 2.0);
+                let _ = icon::draw_ring(cr, size, size * 0.07, *progress, *tone);
+
+                // Shrink longer readings (`1:02:03`, `12:34.56`) to stay inside the ring.
+                let scale = match digits.chars().count() {
+                    0..=4 => 0.24,
+                    5 => 0.21,
+                    6 => 0.18,
+                    _ => 0.15,
+                };
+                cr.select_font_face("Sans", FontSlant::Normal, FontWeight::Bold);
+                cr.set_font_size(size * scale);
+                let Ok(extents) = cr.text_extents(digits) else {
+                    return;
+                };
+                cr.move_to(
+                    size / 2.0 - extents.width() / 2.0 - extents.x_bearing(),
+                    size / 2.0 - extents.height() / 2.0 - extents.y_bearing(),
+                );
+                // Follow the theme's text colour.
+                let color = dial.color();
+                cr.set_source_rgba(
+                    f64::from(color.red()),
+                    f64::from(color.green()),
+                    f64::from(color.blue()),
+                    f64::from(color.alpha()),
+                );
+                let _ = cr.show_text(digits);
+            });
+        }
+        root_box.append(&dial);
+
         let remaining_label = Label::builder()
-            .label(&format!(
+            .label(format!(
                 "Remaining: --:-- / Original: {}",
                 format_seconds(original_seconds)
             ))
@@ -118,6 +199,39 @@ impl TimerWindow {
             .build();
         root_box.append(&remaining_label);
 
+        let notice_label = Label::builder()
+            .xalign(0.0)
+            .yalign(0.5)
+            .wrap(true)
+            .visible(false)
+            .build();
+        root_box.append(&notice_label);
+
+        // Only revealed once the alarm goes off.
+        let message_label = Label::builder()
+            .label(message.unwrap_or_default())
+            .css_classes(vec!["message"])
+            .xalign(0.0)
+            .yalign(0.5)
+            .wrap(true)
+            .visible(false)
+            .build();
+        root_box.append(&message_label);
+
+        // Stopwatch laps, newest first
+        let laps_label = Label::builder()
+            .css_classes(vec!["laps"])
+            .xalign(0.0)
+            .yalign(0.0)
+            .build();
+        let laps_scroll = ScrolledWindow::builder()
+            .child(&laps_label)
+            .max_content_height(140)
+            .propagate_natural_height(true)
+            .visible(false)
+            .build();
+        root_box.append(&laps_scroll);
+
         let button_row = Box::builder()
             .orientation(Orientation::Horizontal)
             .spacing(10)
@@ -130,14 +244,22 @@ impl TimerWindow {
             .build();
         button_row.append(&stop_button);
 
-        let mut pause_buttons = Vec::new();
-        for pct in [1_u64, 5, 10] {
-            let btn = Button::builder()
-                .label(&format!("Pause {pct}%"))
-                .hexpand(true)
-                .build();
+        let lap_button = Button::builder()
+            .label("Lap")
+            .hexpand(true)
+            .visible(false)
+            .build();
+        button_row.append(&lap_button);
+
+        let mut snooze_buttons = Vec::new();
+        for &snooze in &config.snooze {
+            let label = match snooze {
+                Snooze::Percent(_) => format!("Pause {snooze}"),
+                Snooze::Seconds(_) => format!("Snooze {snooze}"),
+            };
+            let btn = Button::builder().label(label).hexpand(true).build();
             button_row.append(&btn);
-            pause_buttons.push((pct, btn));
+            snooze_buttons.push((snooze, btn));
         }
 
         root_box.append(&button_row);
@@ -151,11 +273,20 @@ impl TimerWindow {
 
         Self {
             window,
-            pause_buttons,
+            snooze_buttons,
             stop_button,
+            lap_button,
             close_button,
             remaining_label,
-            original_seconds,
+            notice_label,
+            message_label,
+            phase_label,
+            round_label,
+            laps_label,
+            laps_scroll,
+            dial,
+            dial_state,
+            original_seconds: Cell::new(original_seconds),
         }
     }
 
@@ -167,6 +298,14 @@ impl TimerWindow {
         self.window.set_visible(false);
     }
 
+    pub fn is_visible(&self) -> bool {
+        self.window.is_visible()
+    }
+
+    pub fn destroy(&self) {
+        self.window.destroy();
+    }
+
     pub fn connect_stop<F>(&self, handler: F)
     where
         F: Fn() + 'static,
@@ -174,19 +313,24 @@ impl TimerWindow {
         self.stop_button.connect_clicked(move |_| handler());
     }
 
-    pub fn connect_pause<F>(&self, percent: u64, handler: F)
+    pub fn connect_snooze<F>(&self, handler: F)
     where
-        F: Fn() + 'static,
+        F: Fn(Snooze) + 'static,
     {
-        if let Some((_, button)) = self
-            .pause_buttons
-            .iter()
-            .find(|(pct, _)| *pct == percent)
-        {
-            button.connect_clicked(move |_| handler());
+        let handler = Rc::new(handler);
+        for (snooze, button) in &self.snooze_buttons {
+            let (snooze, handler) = (*snooze, Rc::clone(&handler));
+            button.connect_clicked(move |_| handler(snooze));
         }
     }
 
+    pub fn connect_lap<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.lap_button.connect_clicked(move |_| handler());
+    }
+
     pub fn connect_close<F>(&self, handler: F)
     where
         F: Fn() + 'static,
@@ -194,13 +338,275 @@ impl TimerWindow {
         self.close_button.connect_clicked(move |_| handler());
     }
 
-    pub fn set_remaining(&self, seconds: i64) {
+    /// Change the length shown as "Original", when a new phase starts.
+    pub fn set_original(&self, seconds: i64) {
+        self.original_seconds.set(seconds);
+    }
+
+    pub fn set_phase(&self, phase: Option<&str>) {
+        self.phase_label.set_label(phase.unwrap_or_default());
+        self.phase_label.set_visible(phase.is_some());
+    }
+
+    /// Swap the snooze buttons for a Lap button, for a stopwatch.
+    pub fn set_stopwatch(&self, stopwatch: bool) {
+        self.lap_button.set_visible(stopwatch);
+        for (_, button) in &self.snooze_buttons {
+            button.set_visible(!stopwatch);
+        }
+    }
+
+    pub fn set_laps(&self, laps: &[String]) {
+        self.laps_label.set_label(&laps.join("\n"));
+        self.laps_scroll.set_visible(!laps.is_empty());
+    }
+
+    pub fn set_round(&self, round: Option<&str>) {
+        self.round_label.set_label(round.unwrap_or_default());
+        self.round_label.set_visible(round.is_some());
+    }
+
+    /// Reveal the timer's message, if it has one, while the alarm is ringing.
+    pub fn show_message(&self, visible: bool) {
+        self.message_label
+            .set_visible(visible && !self.message_label.label().is_empty());
+    }
+
+    /// Show (or with `None`, hide) a line of text under the remaining time.
+    pub fn set_notice(&self, notice: Option<&str>) {
+        self.notice_label.set_label(notice.unwrap_or_default());
+        self.notice_label.set_visible(notice.is_some());
+    }
+
+    /// Update the ring: `progress` is the share of time left (or, on a
+    /// stopwatch, of the current minute), `digits` the time in its middle.
+    pub fn set_progress(&self, progress: f64, tone: Tone, digits: &str) {
+        let next = (progress.clamp(0.0, 1.0), tone, digits.to_string());
+        let mut state = self.dial_state.borrow_mut();
+        if *state != next {
+            *state = next;
+            drop(state);
+            self.dial.queue_draw();
+        }
+    }
+
+    pub fn set_elapsed(&self, elapsed: &str) {
+        self.remaining_label.set_label(&format!("Elapsed: {elapsed}"));
+    }
+
+    /// Show how long the alarm has been going past the end, e.g. `+3:12 over`.
+    pub fn set_overtime(&self, seconds: i64) {
+        self.remaining_label.add_css_class("overtime");
         self.remaining_label.set_label(&format!(
-            "Remaining: {} / Original: {}",
+            "+{} over / Original: {}",
             format_seconds(seconds),
-            format_seconds(self.original_seconds)
+            format_seconds(self.original_seconds.get())
         ));
     }
+
+    pub fn set_remaining(&self, seconds: i64, target: Option<&str>) {
+        self.remaining_label.remove_css_class("overtime");
+        let remaining = match target {
+            Some(target) => format!("{} (until {target})", format_seconds(seconds)),
+            None => format_seconds(seconds),
+        };
+        self.remaining_label.set_label(&format!(
+            "Remaining: {remaining} / Original: {}",
+            format_seconds(self.original_seconds.get())
+        ));
+    }
+}
+
+/// Height of a countdown pill plus the gap to the next one.
+const PILL_PITCH: i32 = 48;
+
+thread_local! {
+    /// Which stacking positions are taken by countdown pills.
+    static PILL_SLOTS: RefCell<Vec<bool>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A small always-on-top countdown with pause and stop buttons, shown in
+/// place of the tray icon when there is no StatusNotifier host to show it.
+///
+/// Pills of the same process stack away from the anchored edge; those of
+/// separate standalone processes don't know about each other and overlap.
+pub struct CountdownPill {
+    window: ApplicationWindow,
+    time_label: Label,
+    toggle_button: Button,
+    stop_button: Button,
+    slot: usize,
+}
+
+impl CountdownPill {
+    pub fn new(app: &Application, label: Option<&str>, config: &Config) -> Self {
+        let window = ApplicationWindow::builder()
+            .application(app)
+            .title(label.unwrap_or("tytimer"))
+            .resizable(false)
+            .decorated(false)
+            .css_classes(vec!["pill"])
+            .build();
+
+        let slot = PILL_SLOTS.with_borrow_mut(|slots| {
+            let slot = slots.iter().position(|taken| !taken).unwrap_or(slots.len());
+            if slot == slots.len() {
+                slots.push(true);
+            } else {
+                slots[slot] = true;
+            }
+            slot
+        });
+        let edge = place(&window, &config.window);
+        window.set_margin(edge, config.window.margin + slot as i32 * PILL_PITCH);
+
+        set_style(
+            &WidgetExt::display(&window),
+            "pill",
+            "window.pill { background: transparent; }
+             box.pill { background-color: rgba(0, 0, 0, 0.88); color: white; border-radius: 999px; padding: 2px 4px 2px 14px; }
+             box.pill label.time { font-weight: 700; font-feature-settings: \"tnum\"; }
+             box.pill label.warning { color: #f59e0b; }
+             box.pill label.overtime { color: #f87171; }
+             box.pill button { padding: 4px; min-height: 0; min-width: 0; }",
+            STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+
+        let row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .css_classes(vec!["pill"])
+            .build();
+        if let Some(label) = label {
+            row.append(
+                &Label::builder()
+                    .label(label)
+                    .css_classes(vec!["subtitle"])
+                    .build(),
+            );
+        }
+        let time_label = Label::builder()
+            .label("--:--")
+            .css_classes(vec!["time"])
+            .build();
+        row.append(&time_label);
+        let toggle_button = Button::builder()
+            .icon_name("media-playback-pause-symbolic")
+            .tooltip_text("Pause")
+            .css_classes(vec!["flat"])
+            .build();
+        row.append(&toggle_button);
+        let stop_button = Button::builder()
+            .icon_name("media-playback-stop-symbolic")
+            .tooltip_text("Stop")
+            .css_classes(vec!["flat"])
+            .build();
+        row.append(&stop_button);
+        window.set_child(Some(&row));
+
+        window.connect_close_request(|_| glib::Propagation::Stop);
+        window.set_visible(false);
+
+        Self {
+            window,
+            time_label,
+            toggle_button,
+            stop_button,
+            slot,
+        }
+    }
+
+    pub fn connect_toggle<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.toggle_button.connect_clicked(move |_| handler());
+    }
+
+    pub fn connect_stop<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.stop_button.connect_clicked(move |_| handler());
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.window.set_visible(visible);
+    }
+
+    /// Show what the tray icon would.
+    pub fn update(&self, status: &TrayStatus) {
+        let time = match &status.phase {
+            Some(phase) => format!("{phase} · {}", status.time),
+            None => status.time.clone(),
+        };
+        self.time_label.set_label(&time);
+        for (class, on) in [("warning", status.warning), ("overtime", status.overtime)] {
+            if on {
+                self.time_label.add_css_class(class);
+            } else {
+                self.time_label.remove_css_class(class);
+            }
+        }
+        let (icon, tooltip) = match status.running {
+            true => ("media-playback-pause-symbolic", "Pause"),
+            false => ("media-playback-start-symbolic", "Resume"),
+        };
+        self.toggle_button.set_icon_name(icon);
+        self.toggle_button.set_tooltip_text(Some(tooltip));
+    }
+
+    pub fn destroy(&self) {
+        PILL_SLOTS.with_borrow_mut(|slots| slots[self.slot] = false);
+        self.window.destroy();
+    }
+}
+
+/// Anchor a layer-shell window to the configured screen edges, returning the
+/// top or bottom edge it sits against.
+fn place(window: &ApplicationWindow, placement: &WindowConfig) -> Edge {
+    window.init_layer_shell();
+    window.set_layer(match placement.layer {
+        WindowLayer::Top => Layer::Top,
+        WindowLayer::Overlay => Layer::Overlay,
+    });
+    let (vertical, horizontal) = match placement.anchor {
+        Anchor::TopLeft => (Edge::Top, Some(Edge::Left)),
+        Anchor::Top => (Edge::Top, None),
+        Anchor::TopRight => (Edge::Top, Some(Edge::Right)),
+        Anchor::BottomLeft => (Edge::Bottom, Some(Edge::Left)),
+        Anchor::Bottom => (Edge::Bottom, None),
+        Anchor::BottomRight => (Edge::Bottom, Some(Edge::Right)),
+    };
+    for edge in std::iter::once(vertical).chain(horizontal) {
+        window.set_anchor(edge, true);
+        window.set_margin(edge, placement.margin);
+    }
+    vertical
+}
+
+/// Layer the user's CSS over the built-in styles.
+fn add_theme(display: &gtk4::gdk::Display, theme: &ThemeConfig) {
+    set_style(display, "theme", &theme.css(), STYLE_PROVIDER_PRIORITY_USER);
+}
+
+thread_local! {
+    /// The providers behind [`set_style`], by name.
+    static STYLES: RefCell<HashMap<&'static str, CssProvider>> = RefCell::new(HashMap::new());
+}
+
+/// Give `display` the styles `css` under `name`, in place of any given under
+/// that name before. Each name has one provider for the life of the process,
+/// however many windows are opened and closed.
+fn set_style(display: &gtk4::gdk::Display, name: &'static str, css: &str, priority: u32) {
+    STYLES.with_borrow_mut(|styles| {
+        let provider = styles.entry(name).or_insert_with(|| {
+            let provider = CssProvider::new();
+            gtk4::style_context_add_provider_for_display(display, &provider, priority);
+            provider
+        });
+        provider.load_from_data(css);
+    });
 }
 
 fn format_seconds(total_seconds: i64) -> String {
@@ -221,7 +627,7 @@ pub struct SetterWindow {
 }
 
 impl SetterWindow {
-    pub fn new(app: &Application) -> Rc<Self> {
+    pub fn new(app: &Application, config: &Config) -> Rc<Self> {
         let window = ApplicationWindow::builder()
             .application(app)
             .title("tytimer")
@@ -231,16 +637,13 @@ impl SetterWindow {
             .decorated(false)
             .build();
 
-        // Use layer-shell to anchor to top-right, matching TimerWindow
-        window.init_layer_shell();
-        window.set_layer(Layer::Top);
-        window.set_anchor(Edge::Top, true);
-        window.set_anchor(Edge::Right, true);
-        window.set_margin(Edge::Top, 16);
-        window.set_margin(Edge::Right, 16);
+        // Use layer-shell to anchor like TimerWindow
+        place(&window, &config.window);
 
-        let provider = CssProvider::new();
-        let _ = provider.load_from_data(
+        let display = WidgetExt::display(&window);
+        set_style(
+            &display,
+            "setter",
             "window { background-color: rgba(0, 0, 0, 0.88); color: white; }
              button { border-radius: 6px; padding: 12px 14px; font-weight: 600; }
              button.suggested-action { background: #3b82f6; color: white; }
@@ -248,19 +651,15 @@ impl SetterWindow {
              frame { border-radius: 10px; }
              label.title { font-size: 24px; font-weight: 800; }
              label.subtitle { opacity: 0.7; }
-             label.time-display { font-size: 32px; font-weight: 800; padding: 8px 16px; border: 2px solid rgba(59, 130, 246, 0.6); border-radius: 8px; background: rgba(59, 130, 246, 0.1); }
+             label.error { color: #f87171; }
+             entry.time-display { font-size: 32px; font-weight: 800; padding: 8px 16px; border: 2px solid rgba(59, 130, 246, 0.6); border-radius: 8px; background: rgba(59, 130, 246, 0.1); }
              scale { min-height: 50px; }
              scale trough { min-height: 6px; background: rgba(255, 255, 255, 0.2); border-radius: 3px; }
              scale highlight { background: #3b82f6; border-radius: 3px; }
              scale slider { min-width: 20px; min-height: 20px; margin: -7px; background: white; border-radius: 10px; }",
-        );
-
-        let display = WidgetExt::display(&window);
-        gtk4::style_context_add_provider_for_display(
-            &display,
-            &provider,
             STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
+        add_theme(&display, &config.theme);
 
         let root_box = Box::builder()
             .orientation(Orientation::Vertical)
@@ -309,14 +708,16 @@ impl SetterWindow {
         spacer.set_hexpand(true);
         header.append(&spacer);
 
-        // Time display in header
-        let time_label = Label::builder()
-            .label("5:00")
+        // Time display in header; also accepts typed durations like `1h30m`
+        let time_entry = Entry::builder()
+            .text(format_seconds(config.default_duration))
             .css_classes(vec!["time-display"])
+            .width_chars(7)
+            .xalign(0.5)
             .halign(Align::Center)
             .valign(Align::Center)
             .build();
-        header.append(&time_label);
+        header.append(&time_entry);
 
         let close_button = Button::builder()
             .icon_name("window-close")
@@ -326,13 +727,50 @@ impl SetterWindow {
         header.append(&close_button);
         root_box.append(&header);
 
-        // Scale from 0.5 to 120 minutes (0.5 min to 2 hours)
-        let scale = Scale::with_range(Orientation::Horizontal, 0.5, 120.0, 0.5);
-        scale.set_value(5.0);
+        // Slider over the configured range, in seconds
+        let range = &config.setter;
+        let scale = Scale::with_range(
+            Orientation::Horizontal,
+            range.min as f64,
+            range.max as f64,
+            range.step as f64,
+        );
+        scale.set_value(config.default_duration as f64);
         scale.set_draw_value(false);
         scale.set_hexpand(true);
         root_box.append(&scale);
 
+        let name_row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let label_entry = Entry::builder()
+            .placeholder_text("Label (optional)")
+            .hexpand(true)
+            .build();
+        name_row.append(&label_entry);
+        let message_entry = Entry::builder()
+            .placeholder_text("Alarm message (optional)")
+            .hexpand(true)
+            .build();
+        name_row.append(&message_entry);
+        root_box.append(&name_row);
+
+        // One-click buttons for the configured presets
+        let preset_row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .visible(!config.presets.is_empty())
+            .build();
+        root_box.append(&preset_row);
+
+        let error_label = Label::builder()
+            .css_classes(vec!["error"])
+            .xalign(0.0)
+            .visible(false)
+            .build();
+        root_box.append(&error_label);
+
         // Start button
         let start_button = Button::builder()
             .label("Start Timer")
@@ -361,23 +799,63 @@ impl SetterWindow {
             });
         }
 
-        // Update time label when scale changes
-        let time_label_clone = time_label.clone();
+        for (name, preset) in &config.presets {
+            let button = Button::builder()
+                .label(format!(
+                    "{} · {}",
+                    preset.label.as_deref().unwrap_or(name),
+                    format_seconds(preset.duration)
+                ))
+                .hexpand(true)
+                .build();
+            let this_clone = Rc::clone(&this);
+            let name = name.clone();
+            button.connect_clicked(move |_| this_clone.launch(std::slice::from_ref(&name)));
+            preset_row.append(&button);
+        }
+
+        // Update time entry when scale changes
+        let time_entry_clone = time_entry.clone();
         scale.connect_value_changed(move |scale| {
-            let minutes = scale.value();
-            let total_seconds = (minutes * 60.0).round() as i64;
-            time_label_clone.set_label(&format_seconds(total_seconds));
+            let total_seconds = scale.value().round() as i64;
+            time_entry_clone.set_text(&format_seconds(total_seconds));
         });
 
-        // Handle start button click
-        {
+        // Validate typed durations as they are entered
+        let error_label_clone = error_label.clone();
+        time_entry.connect_changed(move |entry| match duration::parse(&entry.text()) {
+            Ok(_) => {
+                entry.remove_css_class("error");
+                error_label_clone.set_visible(false);
+            }
+            Err(err) => {
+                entry.add_css_class("error");
+                error_label_clone.set_label(&err.to_string());
+                error_label_clone.set_visible(true);
+            }
+        });
+
+        // Handle start button click (or Enter in the time entry)
+        let start = {
             let this_clone = Rc::clone(&this);
-            let scale_clone = scale.clone();
-            start_button.connect_clicked(move |_| {
-                let minutes = scale_clone.value();
-                this_clone.start_timer(minutes);
-            });
+            let time_entry_clone = time_entry.clone();
+            let label_entry = label_entry.clone();
+            let message_entry = message_entry.clone();
+            move || {
+                if let Ok(total_seconds) = duration::parse(&time_entry_clone.text()) {
+                    this_clone.start_timer(
+                        total_seconds,
+                        label_entry.text().trim(),
+                        message_entry.text().trim(),
+                    );
+                }
+            }
+        };
+        for entry in [&time_entry, &label_entry, &message_entry] {
+            let start = start.clone();
+            entry.connect_activate(move |_| start());
         }
+        start_button.connect_clicked(move |_| start());
 
         this
     }
@@ -386,21 +864,29 @@ impl SetterWindow {
         self.window.present();
     }
 
-    fn start_timer(&self, minutes: f64) {
+    fn start_timer(&self, total_seconds: i64, label: &str, message: &str) {
+        let mut args = vec![format!("{total_seconds}s")];
+        if !label.is_empty() {
+            args.extend(["--label".to_string(), label.to_string()]);
+        }
+        if !message.is_empty() {
+            args.extend(["--message".to_string(), message.to_string()]);
+        }
+        self.launch(&args);
+    }
+
+    fn launch(&self, args: &[String]) {
         // Close the setter window
         self.window.close();
 
-        // Start the timer in the background using the --no-daemon flag
-        // We need to spawn a new process with the timer
+        // Start the timer the same way the CLI does: handed to the daemon if
+        // one is running, otherwise in its own background process
         let exe = std::env::current_exe().unwrap_or_else(|_| {
             eprintln!("Failed to get current executable path");
             std::path::PathBuf::from("tytimers")
         });
 
-        let result = Command::new(exe)
-            .arg("--no-daemon")
-            .arg(minutes.to_string())
-            .spawn();
+        let result = Command::new(exe).args(args).spawn();
 
         match result {
             Ok(_) => {
@@ -0,0 +1,110 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Days, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::time::SystemTime;
+
+/// Parse a local clock time such as `14:30`, `9:05pm`, `today 18:00` or
+/// `tomorrow 09:00` into the moment it next occurs.
+///
+/// Without a day, a time that has already passed today means tomorrow.
+/// Times skipped by a DST change resolve to the first valid moment after the
+/// gap, and times that occur twice resolve to the first occurrence.
+pub fn parse(input: &str) -> Result<SystemTime> {
+    let text = input.trim();
+    let lower = text.to_ascii_lowercase();
+    let (day, clock) = match lower.split_once(char::is_whitespace) {
+        Some((day @ ("today" | "tomorrow"), clock)) => (Some(day), clock.trim()),
+        _ => (None, lower.as_str()),
+    };
+    let time = parse_clock(clock).map_err(|err| anyhow!("`{text}`: {err}"))?;
+
+    let now = Local::now();
+    let today = now.date_naive();
+    let date = match day {
+        Some("tomorrow") => tomorrow(today)?,
+        _ => today,
+    };
+    let mut moment = resolve_local(date.and_time(time))?;
+    if moment <= now {
+        match day {
+            Some(_) => bail!("`{text}` is already in the past"),
+            None => moment = resolve_local(tomorrow(today)?.and_time(time))?,
+        }
+    }
+    Ok(moment.into())
+}
+
+/// Short local rendering of a moment: `14:30`, or `Tue 09:00` when it is not today.
+pub fn format_local(moment: SystemTime) -> String {
+    let moment: DateTime<Local> = moment.into();
+    if moment.date_naive() == Local::now().date_naive() {
+        moment.format("%H:%M").to_string()
+    } else {
+        moment.format("%a %H:%M").to_string()
+    }
+}
+
+fn tomorrow(today: NaiveDate) -> Result<NaiveDate> {
+    today
+        .checked_add_days(Days::new(1))
+        .ok_or_else(|| anyhow!("date out of range"))
+}
+
+pub fn parse_clock(clock: &str) -> Result<NaiveTime> {
+    let (clock, pm) = if let Some(rest) = clock.strip_suffix("pm") {
+        (rest.trim_end(), Some(true))
+    } else if let Some(rest) = clock.strip_suffix("am") {
+        (rest.trim_end(), Some(false))
+    } else {
+        (clock, None)
+    };
+
+    let mut fields = clock.split(':');
+    let mut next = |name: &str, required: bool| -> Result<Option<u32>> {
+        match fields.next() {
+            Some(field) if !field.is_empty() && field.chars().all(|c| c.is_ascii_digit()) => {
+                Ok(Some(field.parse()?))
+            }
+            None if !required => Ok(None),
+            _ => bail!("expected a time like 14:30 or 2:30pm (bad {name})"),
+        }
+    };
+    let hour = next("hour", true)?.unwrap_or_default();
+    // A bare hour is only unambiguous with am/pm: `2pm`.
+    let minute = next("minute", pm.is_none())?.unwrap_or(0);
+    let second = next("second", false)?.unwrap_or(0);
+    if fields.next().is_some() {
+        bail!("expected a time like 14:30 or 2:30pm (too many fields)");
+    }
+
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => bail!("hour must be 1-12 with am/pm"),
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, second)
+        .ok_or_else(|| anyhow!("{hour}:{minute:02}:{second:02} is not a valid time of day"))
+}
+
+fn resolve_local(naive: NaiveDateTime) -> Result<DateTime<Local>> {
+    resolve(&Local, naive)
+}
+
+/// The moment `naive` names in `zone`, settled the way [`parse`] describes.
+pub fn resolve<Tz: TimeZone>(zone: &Tz, naive: NaiveDateTime) -> Result<DateTime<Tz>> {
+    match zone.from_local_datetime(&naive) {
+        LocalResult::Single(moment) => Ok(moment),
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest),
+        LocalResult::None => {
+            // Skipped by a DST jump: walk forward to where local time resumes.
+            (1..=4 * 60)
+                .filter_map(|minutes| naive.checked_add_signed(chrono::Duration::minutes(minutes)))
+                .find_map(|later| match zone.from_local_datetime(&later) {
+                    LocalResult::Single(moment) => Some(moment),
+                    LocalResult::Ambiguous(earliest, _) => Some(earliest),
+                    LocalResult::None => None,
+                })
+                .ok_or_else(|| anyhow!("{naive} does not exist in the timezone"))
+        }
+    }
+}
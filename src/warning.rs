@@ -0,0 +1,56 @@
+use crate::duration;
+use anyhow::{bail, Result};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+/// A heads-up before a timer ends (`--warn 5m,50%`): a fixed time left, or
+/// a share of the timer's length left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Warning {
+    Left(i64),
+    Percent(f64),
+}
+
+impl Warning {
+    /// Seconds left on a timer of `original` seconds when the warning is due.
+    pub fn seconds_left(self, original: i64) -> i64 {
+        match self {
+            Warning::Left(seconds) => seconds,
+            Warning::Percent(percent) => ((original as f64) * percent / 100.0).round() as i64,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let Some(percent) = text.trim().strip_suffix('%') else {
+            return Ok(Warning::Left(duration::parse(text)?));
+        };
+        match percent.trim().parse::<f64>() {
+            Ok(percent) if percent > 0.0 && percent < 100.0 => Ok(Warning::Percent(percent)),
+            Ok(_) => bail!("`{text}` must be above 0% and below 100%"),
+            Err(_) => bail!("`{text}` is not a percentage"),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Left(seconds) => f.write_str(&duration::format(*seconds)),
+            Warning::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+impl Serialize for Warning {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Warning {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Warning::parse(&text).map_err(de::Error::custom)
+    }
+}